@@ -0,0 +1,348 @@
+use crate::auth::{AuthEndpoints, Login, LoginError, MICROSOFT_CLIENT_ID};
+use crate::secret::{self, Protection, ProtectionKind, Sealed};
+use crate::yggdrasil::YggdrasilAccount;
+use crate::OfflineUuidScheme;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 一个账号。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Account {
+    /// 微软账号
+    Microsoft(Login),
+    /// 离线账号
+    Offline { name: String, uuid: String },
+    /// 第三方 Yggdrasil 验证服务器的账号
+    Yggdrasil(YggdrasilAccount),
+}
+
+impl Account {
+    /// 用玩家名创建一个离线账号，UUID 与原版相同。
+    pub fn offline(name: &str) -> Account {
+        Account::offline_with(name, OfflineUuidScheme::Vanilla)
+    }
+
+    /// 用玩家名创建一个离线账号。
+    ///
+    /// `scheme`: 生成 UUID 的方式，旧的存档需要使用 `OfflineUuidScheme::Legacy`。
+    pub fn offline_with(name: &str, scheme: OfflineUuidScheme) -> Account {
+        Account::Offline {
+            name: name.to_string(),
+            uuid: scheme.uuid(name),
+        }
+    }
+
+    /// 玩家名。
+    pub fn name(&self) -> &str {
+        match self {
+            Account::Microsoft(login) => login.name(),
+            Account::Offline { name, .. } => name,
+            Account::Yggdrasil(account) => &account.name,
+        }
+    }
+
+    /// 不带连字符的 UUID。
+    pub fn uuid(&self) -> &str {
+        match self {
+            Account::Microsoft(login) => login.uuid(),
+            Account::Offline { uuid, .. } => uuid,
+            Account::Yggdrasil(account) => &account.uuid,
+        }
+    }
+
+    /// 替换 `${auth_access_token}` 的访问令牌，离线账号为 `0`。
+    pub fn access_token(&self) -> &str {
+        match self {
+            Account::Microsoft(login) => login.access_token(),
+            Account::Offline { .. } => "0",
+            Account::Yggdrasil(account) => &account.access_token,
+        }
+    }
+
+    /// 替换 `${auth_session}` 的值，1.6 之前的版本使用。
+    pub fn session(&self) -> String {
+        match self {
+            Account::Offline { .. } => String::from("0"),
+            _ => format!("token:{}:{}", self.access_token(), self.uuid()),
+        }
+    }
+
+    /// 替换 `${user_type}` 的值。
+    pub fn user_type(&self) -> &'static str {
+        match self {
+            Account::Microsoft(_) => "msa",
+            Account::Offline { .. } => "legacy",
+            Account::Yggdrasil(_) => "mojang",
+        }
+    }
+
+    /// 替换 `${auth_xuid}` 的值，只有微软账号有 Xbox 用户 id。
+    pub fn xuid(&self) -> String {
+        match self {
+            Account::Microsoft(login) => login.xuid().unwrap_or_else(|| String::from("0")),
+            _ => String::from("0"),
+        }
+    }
+
+    /// 替换 `${clientid}` 的值。
+    pub fn client_id(&self) -> &str {
+        match self {
+            Account::Microsoft(_) => MICROSOFT_CLIENT_ID,
+            Account::Offline { .. } => "0",
+            Account::Yggdrasil(account) => &account.client_token,
+        }
+    }
+
+    /// 启动前是否需要刷新访问令牌。
+    ///
+    /// Yggdrasil 账号的令牌是否有效只能由验证服务器判断，因此总是需要检查。
+    pub fn needs_refresh(&self) -> bool {
+        match self {
+            Account::Microsoft(login) => login.is_expired(),
+            Account::Offline { .. } => false,
+            Account::Yggdrasil(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccountFile {
+    /// 选中的账号的 UUID
+    #[serde(default)]
+    selected: Option<String>,
+    #[serde(default)]
+    accounts: Vec<Account>,
+}
+
+/// 账号文件的内容。没有 `protection` 字段的是加密功能出现之前的明文文件。
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "protection", rename_all = "lowercase")]
+enum StoredFile {
+    Plaintext(AccountFile),
+    Passphrase(Sealed),
+    Keyring(Sealed),
+}
+
+/// 读取账号文件。
+///
+/// 返回: 文件的保护方式和内容，旧的明文文件返回 `ProtectionKind::Legacy`。
+fn read_stored_file(path: &str) -> Result<(ProtectionKind, serde_json::Value), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| format!("读取账号文件 {} 时发生错误: {}", path, err))?;
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|err| format!("解析账号文件 {} 时发生错误: {}", path, err))?;
+
+    let kind = match value.get("protection") {
+        Some(kind) => serde_json::from_value(kind.clone())
+            .map_err(|err| format!("解析账号文件 {} 时发生错误: {}", path, err))?,
+        None => ProtectionKind::Legacy,
+    };
+
+    Ok((kind, value))
+}
+
+/// 保存在磁盘上的账号列表。
+///
+/// 账号以 UUID 区分，同一个 UUID 的账号只保存一个。
+/// 访问令牌和刷新令牌会按 `protection` 加密后保存。
+#[derive(Debug, Clone)]
+pub struct AccountStore {
+    /// 账号文件的路径
+    pub path: PathBuf,
+    pub accounts: Vec<Account>,
+    /// 选中的账号的 UUID
+    pub selected: Option<String>,
+    /// 保存时使用的保护方式，修改后下次保存时生效
+    pub protection: Protection,
+}
+
+impl AccountStore {
+    /// 创建一个空的账号列表。
+    ///
+    /// `path`: 账号文件的路径。
+    ///
+    /// `protection`: 保存时使用的保护方式。
+    pub fn new(path: &str, protection: Protection) -> AccountStore {
+        AccountStore {
+            path: PathBuf::from(path),
+            accounts: vec![],
+            selected: None,
+            protection,
+        }
+    }
+
+    /// 获取账号文件使用的保护方式，用于决定是否需要询问密码。
+    ///
+    /// 返回: 文件不存在时返回 `None`。
+    pub fn stored_protection(path: &str) -> Result<Option<ProtectionKind>, String> {
+        if !Path::new(path).is_file() {
+            return Ok(None);
+        }
+
+        Ok(Some(read_stored_file(path)?.0))
+    }
+
+    /// 读取账号文件，文件不存在时返回空的列表。
+    ///
+    /// 明文的账号文件在 `protection` 不是 `Protection::Plaintext` 时会立即按 `protection` 重新保存，
+    /// 加密的账号文件必须使用与保存时相同的保护方式。
+    ///
+    /// `path`: 账号文件的路径。
+    ///
+    /// `protection`: 保护方式，用密码加密时为用户输入的密码。
+    pub fn load(path: &str, protection: Protection) -> Result<AccountStore, String> {
+        let mut store = AccountStore::new(path, protection);
+
+        if !Path::new(path).is_file() {
+            return Ok(store);
+        }
+
+        let (kind, value) = read_stored_file(path)?;
+        let parse_error =
+            |err: serde_json::Error| format!("解析账号文件 {} 时发生错误: {}", path, err);
+
+        let file: AccountFile = match kind {
+            ProtectionKind::Legacy => serde_json::from_value(value).map_err(parse_error)?,
+            _ => match serde_json::from_value(value).map_err(parse_error)? {
+                StoredFile::Plaintext(file) => file,
+                StoredFile::Passphrase(sealed) | StoredFile::Keyring(sealed) => {
+                    if store.protection.kind() != kind {
+                        return Err(format!("账号文件 {} 使用了其他的保护方式", path));
+                    }
+
+                    let data = secret::open(&sealed, &store.protection)?;
+                    serde_json::from_slice(&data).map_err(parse_error)?
+                }
+            },
+        };
+
+        store.accounts = file.accounts;
+        store.selected = file.selected;
+
+        // 迁移旧的明文账号文件
+        let plaintext = matches!(kind, ProtectionKind::Legacy | ProtectionKind::Plaintext);
+        if plaintext && store.protection != Protection::Plaintext {
+            store.save()?;
+        }
+
+        Ok(store)
+    }
+
+    /// 保存到账号文件。
+    pub fn save(&self) -> Result<(), String> {
+        let file = AccountFile {
+            selected: self.selected.clone(),
+            accounts: self.accounts.clone(),
+        };
+
+        let stored = match &self.protection {
+            Protection::Plaintext => StoredFile::Plaintext(file),
+            protection => {
+                let data = serde_json::to_vec(&file).map_err(|err| err.to_string())?;
+                let sealed = secret::seal(&data, protection)?;
+
+                match protection {
+                    Protection::Keyring => StoredFile::Keyring(sealed),
+                    _ => StoredFile::Passphrase(sealed),
+                }
+            }
+        };
+        let text = serde_json::to_string_pretty(&stored).map_err(|err| err.to_string())?;
+
+        write_atomic(&self.path, text.as_bytes())
+    }
+
+    /// 添加一个账号并选中它。已经存在同一个 UUID 的账号时替换它。
+    pub fn add(&mut self, account: Account) {
+        let uuid = account.uuid().to_string();
+
+        match self.accounts.iter_mut().find(|x| x.uuid() == uuid) {
+            Some(exist) => *exist = account,
+            None => self.accounts.push(account),
+        }
+
+        self.selected = Some(uuid);
+    }
+
+    /// 删除一个账号。
+    ///
+    /// 返回: 被删除的账号。
+    pub fn remove(&mut self, uuid: &str) -> Option<Account> {
+        let index = self.accounts.iter().position(|x| x.uuid() == uuid)?;
+
+        if self.selected.as_deref() == Some(uuid) {
+            self.selected = None;
+        }
+
+        Some(self.accounts.remove(index))
+    }
+
+    /// 选中一个账号。
+    ///
+    /// 返回: 是否存在这个账号。
+    pub fn select(&mut self, uuid: &str) -> bool {
+        if self.accounts.iter().any(|x| x.uuid() == uuid) {
+            self.selected = Some(uuid.to_string());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 选中的账号。
+    pub fn selected(&self) -> Option<&Account> {
+        let uuid = self.selected.as_deref()?;
+        self.accounts.iter().find(|x| x.uuid() == uuid)
+    }
+
+    /// 刷新选中的账号的访问令牌并替换它。
+    ///
+    /// 微软账号在访问令牌过期时刷新，Yggdrasil 账号在验证服务器认为令牌无效时刷新。
+    /// 不会自动保存。
+    ///
+    /// 返回: 是否刷新了账号。
+    pub async fn refresh_selected(
+        &mut self,
+        endpoints: &AuthEndpoints,
+    ) -> Result<bool, LoginError> {
+        let account = match self.selected() {
+            Some(Account::Microsoft(login)) if login.is_expired() => {
+                Account::Microsoft(login.refresh(endpoints).await?)
+            }
+            Some(Account::Yggdrasil(account)) if !account.validate().await? => {
+                Account::Yggdrasil(account.refresh().await?)
+            }
+            _ => return Ok(false),
+        };
+
+        self.add(account);
+        Ok(true)
+    }
+}
+
+/// 写入文件。先写入同一文件夹下的临时文件再替换原来的文件，
+/// 写入中途失败时原来的文件保持不变。
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if let Err(err) = std::fs::create_dir_all(parent) {
+            return Err(format!("{}: {}", parent.display(), err));
+        }
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    if let Err(err) = std::fs::write(&temp, contents) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(format!("写入 {} 时发生错误: {}", temp.display(), err));
+    }
+
+    if let Err(err) = std::fs::rename(&temp, path) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(format!("写入 {} 时发生错误: {}", path.display(), err));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,608 @@
+use crate::post::Post;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use reqwest::header::HeaderMap;
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 微软登录使用的客户端 id。
+pub const MICROSOFT_CLIENT_ID: &str = "00000000402b5328";
+
+/// 登录过程中用到的各个接口的地址。
+///
+/// 默认为官方地址，测试时可以指向本地的模拟服务器。
+#[derive(Debug, Clone)]
+pub struct AuthEndpoints {
+    pub device_code: String,
+    pub oauth_token: String,
+    pub xbox_authenticate: String,
+    pub xsts_authorize: String,
+    pub login_with_xbox: String,
+    pub entitlements: String,
+    pub profile: String,
+}
+
+impl Default for AuthEndpoints {
+    fn default() -> Self {
+        AuthEndpoints {
+            device_code: String::from("https://login.live.com/oauth20_connect.srf"),
+            oauth_token: String::from("https://login.live.com/oauth20_token.srf"),
+            xbox_authenticate: String::from("https://user.auth.xboxlive.com/user/authenticate"),
+            xsts_authorize: String::from("https://xsts.auth.xboxlive.com/xsts/authorize"),
+            login_with_xbox: String::from(
+                "https://api.minecraftservices.com/authentication/login_with_xbox",
+            ),
+            entitlements: String::from("https://api.minecraftservices.com/entitlements/mcstore"),
+            profile: String::from("https://api.minecraftservices.com/minecraft/profile"),
+        }
+    }
+}
+
+impl AuthEndpoints {
+    /// 所有接口都在同一个地址下，路径与官方相同。用于连接本地的模拟服务器。
+    ///
+    /// `root`: 例如 `http://127.0.0.1:8080`。
+    pub fn with_root(root: &str) -> AuthEndpoints {
+        let root = root.trim_end_matches('/');
+
+        AuthEndpoints {
+            device_code: format!("{}/oauth20_connect.srf", root),
+            oauth_token: format!("{}/oauth20_token.srf", root),
+            xbox_authenticate: format!("{}/user/authenticate", root),
+            xsts_authorize: format!("{}/xsts/authorize", root),
+            login_with_xbox: format!("{}/authentication/login_with_xbox", root),
+            entitlements: format!("{}/entitlements/mcstore", root),
+            profile: format!("{}/minecraft/profile", root),
+        }
+    }
+}
+
+/// 登录时发生的错误。
+#[derive(Debug)]
+pub enum LoginError {
+    /// 网络错误或无法解析的响应。
+    Http(reqwest::Error),
+    /// 某一步的接口返回了错误。
+    Service {
+        step: &'static str,
+        status: u16,
+        message: String,
+    },
+    /// XSTS 授权失败，附带 `XErr` 错误码。
+    Xsts(u64),
+    /// 账号没有购买 Minecraft。
+    NotOwned,
+    /// 设备代码已过期，用户没有在有效期内完成登录。
+    DeviceCodeExpired,
+    /// 用户拒绝了授权。
+    DeviceCodeDeclined,
+    /// 等待用户登录时被取消。
+    Cancelled,
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoginError::Http(err) => write!(f, "网络错误: {}", err),
+            LoginError::Service {
+                step,
+                status,
+                message,
+            } => write!(f, "{} 失败 ({}): {}", step, status, message),
+            LoginError::Xsts(2148916233) => write!(f, "这个微软账号没有 Xbox 账号, 请先注册"),
+            LoginError::Xsts(2148916235) => write!(f, "Xbox Live 在你所在的地区不可用"),
+            LoginError::Xsts(2148916236) | LoginError::Xsts(2148916237) => {
+                write!(f, "这个账号需要在 Xbox 网站上完成成人验证")
+            }
+            LoginError::Xsts(2148916238) => {
+                write!(f, "这个账号是儿童账号, 需要由家长加入家庭组")
+            }
+            LoginError::Xsts(code) => write!(f, "XSTS 授权失败, 错误码 {}", code),
+            LoginError::NotOwned => write!(f, "这个账号没有购买 Minecraft"),
+            LoginError::DeviceCodeExpired => write!(f, "设备代码已过期, 请重新登录"),
+            LoginError::DeviceCodeDeclined => write!(f, "你拒绝了授权"),
+            LoginError::Cancelled => write!(f, "登录已取消"),
+        }
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+impl From<reqwest::Error> for LoginError {
+    fn from(err: reqwest::Error) -> Self {
+        LoginError::Http(err)
+    }
+}
+
+/// 微软 OAuth 接口返回的令牌。
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    #[serde(default)]
+    pub expires_in: u64,
+}
+
+/// 设备代码登录时，需要展示给用户的信息。
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    /// 用户需要输入的短代码
+    pub user_code: String,
+    pub device_code: String,
+    /// 用户需要打开的链接，例如 `https://www.microsoft.com/link`
+    pub verification_uri: String,
+    /// 有效期，单位为秒
+    pub expires_in: u64,
+    /// 轮询的间隔，单位为秒
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// 轮询一次设备代码的结果。
+#[derive(Debug, Clone)]
+pub enum DeviceCodePoll {
+    /// 用户还没有完成登录
+    Pending,
+    /// 轮询太频繁，需要增加间隔
+    SlowDown,
+    /// 用户已经完成登录
+    Token(OAuthToken),
+}
+
+#[derive(Debug, Deserialize)]
+struct OAuthError {
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxToken {
+    token: String,
+    display_claims: XboxDisplayClaims,
+}
+
+#[derive(Debug, Deserialize)]
+struct XboxDisplayClaims {
+    xui: Vec<XboxUserInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XboxUserInfo {
+    uhs: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct XstsError {
+    #[serde(rename = "XErr")]
+    xerr: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+    #[serde(default)]
+    expires_in: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entitlements {
+    #[serde(default)]
+    items: Vec<Entitlement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entitlement {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    id: String,
+    name: String,
+}
+
+/// 检查响应的状态码，成功时解析响应内容。
+async fn parse_response<T: DeserializeOwned>(
+    step: &'static str,
+    response: Response,
+) -> Result<T, LoginError> {
+    let status = response.status();
+
+    if !status.is_success() {
+        return Err(LoginError::Service {
+            step,
+            status: status.as_u16(),
+            message: response.text().await.unwrap_or_default(),
+        });
+    }
+
+    Ok(response.json::<T>().await?)
+}
+
+fn form_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Content-Type",
+        "application/x-www-form-urlencoded".parse().unwrap(),
+    );
+    headers
+}
+
+fn json_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", "application/json".parse().unwrap());
+    headers.insert("Accept", "application/json".parse().unwrap());
+    headers
+}
+
+fn bearer_headers(token: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "Authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    headers
+}
+
+/// 向微软的 OAuth 令牌接口提交表单。
+///
+/// `form`: 表单的内容，`client_id` 会自动加上。
+pub async fn request_oauth_token(
+    endpoints: &AuthEndpoints,
+    form: &[(&str, &str)],
+) -> Result<OAuthToken, LoginError> {
+    let data = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("client_id", MICROSOFT_CLIENT_ID)
+        .extend_pairs(form)
+        .finish();
+
+    let poster = Post::new();
+    let response = poster
+        .post(&endpoints.oauth_token, form_headers(), data)
+        .await?;
+
+    parse_response("获取微软令牌", response).await
+}
+
+/// 申请一个设备代码。
+pub async fn request_device_code(endpoints: &AuthEndpoints) -> Result<DeviceCode, LoginError> {
+    let data = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("client_id", MICROSOFT_CLIENT_ID)
+        .append_pair("scope", "service::user.auth.xboxlive.com::MBI_SSL")
+        .append_pair("response_type", "device_code")
+        .finish();
+
+    let poster = Post::new();
+    let response = poster
+        .post(&endpoints.device_code, form_headers(), data)
+        .await?;
+
+    parse_response("申请设备代码", response).await
+}
+
+/// 轮询一次设备代码是否已经完成登录。
+pub async fn poll_device_code(
+    endpoints: &AuthEndpoints,
+    device_code: &DeviceCode,
+) -> Result<DeviceCodePoll, LoginError> {
+    let data = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("client_id", MICROSOFT_CLIENT_ID)
+        .append_pair("grant_type", "urn:ietf:params:oauth:grant-type:device_code")
+        .append_pair("device_code", &device_code.device_code)
+        .finish();
+
+    let poster = Post::new();
+    let response = poster
+        .post(&endpoints.oauth_token, form_headers(), data)
+        .await?;
+
+    if response.status().is_success() {
+        return Ok(DeviceCodePoll::Token(response.json().await?));
+    }
+
+    let status = response.status().as_u16();
+    let text = response.text().await.unwrap_or_default();
+    let error = match serde_json::from_str::<OAuthError>(&text) {
+        Ok(result) => result.error,
+        Err(_) => String::new(),
+    };
+
+    match error.as_str() {
+        "authorization_pending" => Ok(DeviceCodePoll::Pending),
+        "slow_down" => Ok(DeviceCodePoll::SlowDown),
+        "expired_token" | "bad_verification_code" => Err(LoginError::DeviceCodeExpired),
+        "authorization_declined" | "access_denied" => Err(LoginError::DeviceCodeDeclined),
+        _ => Err(LoginError::Service {
+            step: "获取微软令牌",
+            status,
+            message: text,
+        }),
+    }
+}
+
+/// 当前的 unix 时间戳，单位为秒。
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+/// 一个已经登录的账号。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Login {
+    logged: bool,
+    name: String,
+    uuid: String,
+    access_token: String,
+    refresh_token: String,
+    /// Minecraft 访问令牌过期的 unix 时间戳，单位为秒
+    #[serde(default)]
+    expires_at: u64,
+}
+
+impl Login {
+    pub fn new() -> Login {
+        Login {
+            logged: false,
+            name: String::new(),
+            uuid: String::new(),
+            access_token: String::new(),
+            refresh_token: String::new(),
+            expires_at: 0,
+        }
+    }
+
+    pub fn is_logged(&self) -> bool {
+        self.logged
+    }
+
+    /// 玩家名。
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 不带连字符的 UUID。
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    /// Minecraft 的访问令牌。
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// Xbox 用户 id，从 Minecraft 访问令牌 (JWT) 的 `xuid` 中读取。
+    pub fn xuid(&self) -> Option<String> {
+        let payload = self.access_token.split('.').nth(1)?;
+        let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+        let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+
+        match claims.get("xuid")? {
+            serde_json::Value::String(xuid) => Some(xuid.clone()),
+            serde_json::Value::Number(xuid) => Some(xuid.to_string()),
+            _ => None,
+        }
+    }
+
+    /// 微软的刷新令牌。
+    pub fn refresh_token(&self) -> &str {
+        &self.refresh_token
+    }
+
+    /// Minecraft 的访问令牌是否已经过期或即将在 5 分钟内过期。
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now() + 300
+    }
+
+    /// 用保存的刷新令牌重新登录，得到新的 Minecraft 访问令牌。
+    pub async fn refresh(&self, endpoints: &AuthEndpoints) -> Result<Login, LoginError> {
+        let token = request_oauth_token(
+            endpoints,
+            &[
+                ("refresh_token", &self.refresh_token),
+                ("grant_type", "refresh_token"),
+                ("scope", "service::user.auth.xboxlive.com::MBI_SSL"),
+            ],
+        )
+        .await?;
+
+        let mut login = Login::login_from_microsoft_token(&token, endpoints).await?;
+
+        // 微软不一定返回新的刷新令牌，此时旧的仍然有效
+        if login.refresh_token.is_empty() {
+            login.refresh_token = self.refresh_token.clone();
+        }
+
+        Ok(login)
+    }
+
+    /// 用浏览器登录后重定向链接中的 `code` 登录。
+    pub async fn login_from_microsoft(code: String) -> Result<Login, LoginError> {
+        Login::login_from_microsoft_with(&code, &AuthEndpoints::default()).await
+    }
+
+    /// 与 `login_from_microsoft` 相同，但使用指定的接口地址。
+    pub async fn login_from_microsoft_with(
+        code: &str,
+        endpoints: &AuthEndpoints,
+    ) -> Result<Login, LoginError> {
+        let token = request_oauth_token(
+            endpoints,
+            &[
+                ("code", code),
+                ("grant_type", "authorization_code"),
+                ("redirect_uri", "https://login.live.com/oauth20_desktop.srf"),
+                ("scope", "service::user.auth.xboxlive.com::MBI_SSL"),
+            ],
+        )
+        .await?;
+
+        Login::login_from_microsoft_token(&token, endpoints).await
+    }
+
+    /// 等待用户用设备代码完成登录，然后完成后续的登录。
+    ///
+    /// 按 `device_code.interval` 轮询，直到用户完成登录、拒绝授权或设备代码过期。
+    ///
+    /// `cancel`: 设为 `true` 时在下一次轮询前停止等待，返回 `LoginError::Cancelled`。
+    pub async fn login_from_device_code(
+        device_code: &DeviceCode,
+        endpoints: &AuthEndpoints,
+        cancel: &AtomicBool,
+    ) -> Result<Login, LoginError> {
+        let deadline = Instant::now() + Duration::from_secs(device_code.expires_in);
+        let mut interval = Duration::from_secs(device_code.interval);
+
+        let token = loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(LoginError::Cancelled);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(LoginError::DeviceCodeExpired);
+            }
+
+            match poll_device_code(endpoints, device_code).await? {
+                DeviceCodePoll::Token(token) => break token,
+                DeviceCodePoll::Pending => {}
+                DeviceCodePoll::SlowDown => interval += Duration::from_secs(5),
+            }
+
+            tokio::time::sleep(interval).await;
+        };
+
+        Login::login_from_microsoft_token(&token, endpoints).await
+    }
+
+    /// 用微软的令牌完成后续的登录: Xbox Live 认证、XSTS 授权、Minecraft 登录、检查是否购买、获取档案。
+    ///
+    /// `token`: 微软 OAuth 接口返回的令牌。
+    pub async fn login_from_microsoft_token(
+        token: &OAuthToken,
+        endpoints: &AuthEndpoints,
+    ) -> Result<Login, LoginError> {
+        let poster = Post::new();
+
+        // ----- Xbox Live ----- //
+
+        let data = json!({
+            "Properties": {
+                "AuthMethod": "RPS",
+                "SiteName": "user.auth.xboxlive.com",
+                "RpsTicket": format!("d={}", token.access_token),
+            },
+            "RelyingParty": "http://auth.xboxlive.com",
+            "TokenType": "JWT",
+        })
+        .to_string();
+
+        let response = poster
+            .post(&endpoints.xbox_authenticate, json_headers(), data)
+            .await?;
+        let xbox: XboxToken = parse_response("Xbox Live 认证", response).await?;
+
+        // ----- XSTS ----- //
+
+        let data = json!({
+            "Properties": {
+                "SandboxId": "RETAIL",
+                "UserTokens": [&xbox.token],
+            },
+            "RelyingParty": "rp://api.minecraftservices.com/",
+            "TokenType": "JWT",
+        })
+        .to_string();
+
+        let response = poster
+            .post(&endpoints.xsts_authorize, json_headers(), data)
+            .await?;
+
+        // 401 时返回的 XErr 说明了原因
+        if response.status().as_u16() == 401 {
+            let text = response.text().await.unwrap_or_default();
+            return Err(match serde_json::from_str::<XstsError>(&text) {
+                Ok(err) => LoginError::Xsts(err.xerr),
+                Err(_) => LoginError::Service {
+                    step: "XSTS 授权",
+                    status: 401,
+                    message: text,
+                },
+            });
+        }
+
+        let xsts: XboxToken = parse_response("XSTS 授权", response).await?;
+        let uhs = match xsts.display_claims.xui.first() {
+            Some(result) => result.uhs.clone(),
+            None => {
+                return Err(LoginError::Service {
+                    step: "XSTS 授权",
+                    status: 200,
+                    message: String::from("缺少 uhs"),
+                })
+            }
+        };
+
+        // ----- Minecraft ----- //
+
+        let data = json!({
+            "identityToken": format!("XBL3.0 x={};{}", uhs, xsts.token),
+        })
+        .to_string();
+
+        let response = poster
+            .post(&endpoints.login_with_xbox, json_headers(), data)
+            .await?;
+        let minecraft: MinecraftToken = parse_response("Minecraft 登录", response).await?;
+
+        let response = poster
+            .get_with_headers(
+                &endpoints.entitlements,
+                bearer_headers(&minecraft.access_token),
+            )
+            .await?;
+        let entitlements: Entitlements = parse_response("检查游戏所有权", response).await?;
+
+        if !entitlements
+            .items
+            .iter()
+            .any(|x| x.name == "game_minecraft" || x.name == "product_minecraft")
+        {
+            return Err(LoginError::NotOwned);
+        }
+
+        let response = poster
+            .get_with_headers(&endpoints.profile, bearer_headers(&minecraft.access_token))
+            .await?;
+
+        // 购买了游戏但还没有创建角色
+        if response.status().as_u16() == 404 {
+            return Err(LoginError::Service {
+                step: "获取档案",
+                status: 404,
+                message: String::from("这个账号还没有创建角色, 请先在官方启动器中设置玩家名"),
+            });
+        }
+
+        let profile: Profile = parse_response("获取档案", response).await?;
+
+        Ok(Login {
+            logged: true,
+            name: profile.name,
+            uuid: profile.id,
+            access_token: minecraft.access_token,
+            refresh_token: token.refresh_token.clone(),
+            expires_at: now() + minecraft.expires_in,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// 替换一个参数中的所有 `${...}`。
+///
+/// 每个参数单独替换，替换后的值即使含有空格也仍然是同一个参数。没有对应值的 `${...}` 保持原样。
+///
+/// `template`: 版本 json 中的参数，例如 `-Djava.library.path=${natives_directory}`。
+///
+/// `values`: 占位符名称 (不含 `${}`) 到值的映射。
+pub fn substitute(template: &str, values: &HashMap<&str, OsString>) -> OsString {
+    let mut result = OsString::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("${") {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        result.push(&rest[..start]);
+        match values.get(&rest[start + 2..end]) {
+            Some(value) => result.push(value),
+            None => result.push(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+
+    result.push(rest);
+    result
+}
+
+/// 启动游戏的完整命令。
+#[derive(Debug, Clone)]
+pub struct LaunchCommand {
+    /// java 可执行文件
+    pub program: OsString,
+    /// jvm 参数、主类和游戏参数，每一项都是一个独立的参数
+    pub args: Vec<OsString>,
+    /// 游戏的工作目录，为 `None` 时使用启动器的当前目录
+    pub current_dir: Option<PathBuf>,
+}
+
+impl LaunchCommand {
+    /// 生成 `std::process::Command`，不设置标准输入输出。
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+        command
+    }
+}
+
+/// 按 shell 的规则给参数加上引号，仅用于显示。
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "-_=./:,+@%".contains(x))
+    {
+        return arg.into_owned();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+impl fmt::Display for LaunchCommand {
+    /// 每个参数按 shell 的规则加上引号，可以直接复制到终端中执行。
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(dir) = &self.current_dir {
+            write!(f, "cd {} && ", quote(dir.as_os_str()))?;
+        }
+
+        write!(f, "{}", quote(&self.program))?;
+
+        for arg in &self.args {
+            write!(f, " {}", quote(arg))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 自动分配内存时的下限，单位 MiB
+pub const AUTO_MEMORY_MIN: u64 = 1024;
+
+/// 自动分配内存时的上限，单位 MiB
+pub const AUTO_MEMORY_MAX: u64 = 8192;
+
+/// 最大内存至少为这个值，否则游戏无法启动，单位 MiB
+pub const MEMORY_LOWER_LIMIT: u64 = 256;
+
+/// 启动游戏时的 jvm 设置。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JvmOptions {
+    /// 初始内存 `-Xms`，单位 MiB
+    pub min_memory: Option<u64>,
+    /// 最大内存 `-Xmx`，单位 MiB，为 `None` 且不自动分配时使用 jvm 的默认值
+    pub max_memory: Option<u64>,
+    /// 没有指定最大内存时按系统内存自动分配
+    pub auto_memory: bool,
+    /// 额外的 jvm 参数，例如 GC 参数或 `-D` 属性
+    pub extra_arguments: Vec<String>,
+}
+
+/// 系统的总内存，单位 MiB。在某些容器中无法获取，此时为 0。
+pub fn system_memory() -> u64 {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    system.total_memory() / 1024 / 1024
+}
+
+/// 按系统内存计算自动分配的最大内存，为系统内存的一半，
+/// 在 `AUTO_MEMORY_MIN` 和 `AUTO_MEMORY_MAX` 之间，但不超过系统内存。
+///
+/// `total`: 系统的总内存，单位 MiB，为 0 (未知) 时使用 `AUTO_MEMORY_MIN`。
+pub fn auto_memory(total: u64) -> u64 {
+    if total == 0 {
+        return AUTO_MEMORY_MIN;
+    }
+
+    (total / 2)
+        .clamp(AUTO_MEMORY_MIN, AUTO_MEMORY_MAX)
+        .min(total)
+}
+
+/// 按 shell 的规则拆分用户输入的 jvm 参数，引号中的空格不会拆分。
+pub fn split_arguments(text: &str) -> Result<Vec<String>, String> {
+    shell_words::split(text).map_err(|err| format!("无法解析 jvm 参数: {}", err))
+}
+
+impl JvmOptions {
+    pub fn new() -> JvmOptions {
+        JvmOptions::default()
+    }
+
+    /// 实际使用的最大内存。
+    ///
+    /// `total`: 系统的总内存，单位 MiB。
+    pub fn resolved_max_memory(&self, total: u64) -> Option<u64> {
+        match self.max_memory {
+            Some(result) => Some(result),
+            None if self.auto_memory => Some(auto_memory(total)),
+            None => None,
+        }
+    }
+
+    /// 检查明显错误的设置。
+    ///
+    /// `total`: 系统的总内存，单位 MiB，为 0 (未知) 时不与系统内存比较。
+    pub fn validate(&self, total: u64) -> Result<(), String> {
+        let max_memory = self.resolved_max_memory(total);
+
+        if let Some(max_memory) = max_memory {
+            if max_memory < MEMORY_LOWER_LIMIT {
+                return Err(format!(
+                    "最大内存 {} MiB 太小, 至少为 {} MiB",
+                    max_memory, MEMORY_LOWER_LIMIT
+                ));
+            }
+
+            if total != 0 && max_memory > total {
+                return Err(format!(
+                    "最大内存 {} MiB 超过了系统内存 {} MiB",
+                    max_memory, total
+                ));
+            }
+        }
+
+        if let (Some(min_memory), Some(max_memory)) = (self.min_memory, max_memory) {
+            if min_memory > max_memory {
+                return Err(format!(
+                    "初始内存 {} MiB 大于最大内存 {} MiB",
+                    min_memory, max_memory
+                ));
+            }
+        }
+
+        for arg in &self.extra_arguments {
+            if !arg.starts_with('-') {
+                return Err(format!("{} 不是 jvm 参数", arg));
+            }
+
+            // 内存由上面的设置决定，同时出现时无法判断哪个生效
+            let memory = arg.starts_with("-Xmx") || arg.starts_with("-Xms");
+            if memory && (self.min_memory.is_some() || max_memory.is_some()) {
+                return Err(format!("{} 与内存设置冲突", arg));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 生成 jvm 参数，放在版本 json 中的 jvm 参数之后，使 `-D` 属性可以覆盖默认值。
+    ///
+    /// `total`: 系统的总内存，单位 MiB。
+    pub fn arguments(&self, total: u64) -> Vec<OsString> {
+        let mut result = vec![];
+
+        if let Some(min_memory) = self.min_memory {
+            result.push(OsString::from(format!("-Xms{}m", min_memory)));
+        }
+
+        if let Some(max_memory) = self.resolved_max_memory(total) {
+            result.push(OsString::from(format!("-Xmx{}m", max_memory)));
+        }
+
+        result.extend(self.extra_arguments.iter().map(OsString::from));
+        result
+    }
+}
@@ -0,0 +1,365 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 崩溃日志的种类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashLogKind {
+    /// 游戏写入 `crash-reports` 的崩溃报告
+    CrashReport,
+    /// JVM 崩溃时写入的 `hs_err_pid*.log`
+    JvmCrash,
+}
+
+/// 崩溃日志中的一节。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrashSection {
+    /// 节的标题，例如 `System Details`，第一节没有标题
+    pub title: String,
+    pub body: String,
+}
+
+/// 一个崩溃日志。
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashLog {
+    #[serde(serialize_with = "serialize_path")]
+    pub path: PathBuf,
+    pub kind: CrashLogKind,
+    #[serde(skip)]
+    pub content: String,
+    pub sections: Vec<CrashSection>,
+}
+
+lazy_static! {
+    /// 崩溃报告的节标题，例如 `-- System Details --`
+    static ref CRASH_REPORT_SECTION: Regex = Regex::new(r"^-- (.+) --$").unwrap();
+    /// JVM 崩溃日志的节标题，例如 `---------------  T H R E A D  ---------------`
+    static ref JVM_CRASH_SECTION: Regex = Regex::new(r"^-{3,}\s+(.+?)\s+-{3,}$").unwrap();
+}
+
+/// 把崩溃日志按节标题拆分。
+pub fn parse_sections(content: &str, kind: CrashLogKind) -> Vec<CrashSection> {
+    let pattern: &Regex = match kind {
+        CrashLogKind::CrashReport => &CRASH_REPORT_SECTION,
+        CrashLogKind::JvmCrash => &JVM_CRASH_SECTION,
+    };
+
+    let mut result = vec![CrashSection {
+        title: String::new(),
+        body: String::new(),
+    }];
+
+    for line in content.lines() {
+        if let Some(captures) = pattern.captures(line.trim_end()) {
+            result.push(CrashSection {
+                // JVM 崩溃日志的标题字母之间有空格
+                title: match kind {
+                    CrashLogKind::CrashReport => captures[1].to_string(),
+                    CrashLogKind::JvmCrash => captures[1].split_whitespace().collect(),
+                },
+                body: String::new(),
+            });
+            continue;
+        }
+
+        let section = result.last_mut().unwrap();
+        section.body.push_str(line);
+        section.body.push('\n');
+    }
+
+    for section in &mut result {
+        section.body = section.body.trim().to_string();
+    }
+
+    result.retain(|x| !x.title.is_empty() || !x.body.is_empty());
+    result
+}
+
+/// 把路径按 UTF-8 写入 JSON，无效的字符替换为 `U+FFFD`，而不是报错。
+fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+impl CrashLog {
+    /// 读取并解析一个崩溃日志。
+    pub fn read(path: &Path, kind: CrashLogKind) -> Result<CrashLog, String> {
+        let content = match std::fs::read(path) {
+            Ok(result) => String::from_utf8_lossy(&result).into_owned(),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        };
+
+        Ok(CrashLog {
+            path: path.to_path_buf(),
+            kind,
+            sections: parse_sections(&content, kind),
+            content,
+        })
+    }
+
+    /// 按标题查找一节。
+    pub fn section(&self, title: &str) -> Option<&CrashSection> {
+        self.sections.iter().find(|x| x.title == title)
+    }
+}
+
+/// `dir` 中名称满足 `filter` 且在 `since` 之后修改的文件。
+fn modified_files(
+    dir: &Path,
+    since: SystemTime,
+    filter: impl Fn(&str) -> bool,
+) -> Vec<(SystemTime, PathBuf)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(result) => result,
+        Err(_) => return vec![],
+    };
+
+    entries
+        .filter_map(|x| x.ok())
+        .filter(|x| filter(&x.file_name().to_string_lossy()))
+        .filter_map(|x| Some((x.metadata().ok()?.modified().ok()?, x.path())))
+        .filter(|(modified, path)| *modified >= since && path.is_file())
+        .collect()
+}
+
+/// 查找游戏启动之后产生的最新的崩溃日志。
+///
+/// 崩溃报告位于 `game_dir/crash-reports`；JVM 崩溃日志写入游戏的工作目录，
+/// 因此同时查找 `game_dir` 和启动器的当前目录。
+///
+/// `since`: 游戏启动的时间，更早的日志属于之前的崩溃。
+pub fn find_crash_log(game_dir: &Path, since: SystemTime) -> Option<CrashLog> {
+    let mut candidates: Vec<(SystemTime, PathBuf, CrashLogKind)> = vec![];
+
+    let reports = modified_files(&game_dir.join("crash-reports"), since, |x| {
+        x.starts_with("crash-") && x.ends_with(".txt")
+    });
+    candidates.extend(
+        reports
+            .into_iter()
+            .map(|(time, path)| (time, path, CrashLogKind::CrashReport)),
+    );
+
+    let mut dirs = vec![game_dir.to_path_buf()];
+    if let Ok(current) = std::env::current_dir() {
+        dirs.push(current);
+    }
+
+    for dir in dirs {
+        let logs = modified_files(&dir, since, |x| {
+            x.starts_with("hs_err_pid") && x.ends_with(".log")
+        });
+        candidates.extend(
+            logs.into_iter()
+                .map(|(time, path)| (time, path, CrashLogKind::JvmCrash)),
+        );
+    }
+
+    let (_, path, kind) = candidates.into_iter().max_by_key(|(time, _, _)| *time)?;
+    CrashLog::read(&path, kind).ok()
+}
+
+/// 已知的崩溃原因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashCause {
+    WrongJavaVersion,
+    MissingNativeLibrary,
+    OutOfMemory,
+    ModConflict,
+    GraphicsDriver,
+}
+
+impl CrashCause {
+    /// 给用户看的说明和建议。
+    pub fn description(&self) -> &'static str {
+        match self {
+            CrashCause::WrongJavaVersion => {
+                "Java 版本不适合这个游戏版本, 请使用版本要求的 Java 或清空 Java 路径以自动选择"
+            }
+            CrashCause::MissingNativeLibrary => {
+                "缺少本地库或本地库与系统不匹配, 请尝试修复游戏文件"
+            }
+            CrashCause::OutOfMemory => "内存不足, 请增大最大内存或关闭其他程序",
+            CrashCause::ModConflict => "模组冲突或缺少前置模组, 请检查模组列表",
+            CrashCause::GraphicsDriver => "显卡驱动不支持需要的 OpenGL 版本, 请更新显卡驱动",
+        }
+    }
+}
+
+/// 一个已知原因的匹配结果。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnosis {
+    pub cause: CrashCause,
+    pub description: String,
+    /// 匹配到的那一行
+    pub evidence: String,
+}
+
+lazy_static! {
+    /// 已知原因和对应的特征，按顺序匹配
+    static ref CRASH_RULES: Vec<(CrashCause, Regex)> = vec![
+        (
+            CrashCause::WrongJavaVersion,
+            Regex::new(concat!(
+                r"UnsupportedClassVersionError",
+                r"|compiled by a more recent version of the Java Runtime",
+                r"|Unsupported class file major version",
+                r"|class jdk\.internal\.loader\.ClassLoaders\$AppClassLoader cannot be cast",
+            ))
+            .unwrap(),
+        ),
+        (
+            CrashCause::MissingNativeLibrary,
+            Regex::new(concat!(
+                r"UnsatisfiedLinkError",
+                r"|no \w+ in java\.library\.path",
+                r"|Failed to locate library",
+            ))
+            .unwrap(),
+        ),
+        (
+            CrashCause::OutOfMemory,
+            Regex::new(concat!(
+                r"java\.lang\.OutOfMemoryError",
+                r"|insufficient memory for the Java Runtime Environment",
+                r"|Could not reserve enough space for .*object heap",
+            ))
+            .unwrap(),
+        ),
+        (
+            CrashCause::ModConflict,
+            Regex::new(concat!(
+                r"DuplicateModsFoundException|Found duplicate mods",
+                r"|ModResolutionException|Incompatible mods? (set|found)",
+                r"|MixinApplyError|Mixin apply (for mod \S+ )?failed",
+                r"|MissingModsException|requires .* which is missing",
+            ))
+            .unwrap(),
+        ),
+        (
+            CrashCause::GraphicsDriver,
+            Regex::new(concat!(
+                r"Pixel format not accelerated|Couldn't set pixel format",
+                r"|GLFW error 6554[2-3]|WGL: The driver does not appear to support OpenGL",
+                r"|(?i:ig[0-9a-z]*icd(32|64)|atio6axx|atioglxx|nvoglv(32|64))\.dll",
+            ))
+            .unwrap(),
+        ),
+    ];
+}
+
+/// 在崩溃日志或游戏输出中查找已知的原因，每种原因最多报告一次。
+pub fn diagnose(text: &str) -> Vec<Diagnosis> {
+    CRASH_RULES
+        .iter()
+        .filter_map(|(cause, pattern)| {
+            let line = text.lines().find(|x| pattern.is_match(x))?;
+
+            Some(Diagnosis {
+                cause: *cause,
+                description: cause.description().to_string(),
+                evidence: line.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 游戏异常退出后的分析结果。
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashAnalysis {
+    /// 进程的退出代码，被信号结束时为 `None`
+    pub exit_code: Option<i32>,
+    pub crash_log: Option<CrashLog>,
+    pub diagnoses: Vec<Diagnosis>,
+}
+
+impl CrashAnalysis {
+    /// 分析一次崩溃。
+    ///
+    /// `output`: 游戏的输出，有些错误 (例如 Java 版本错误) 发生在写入崩溃报告之前，只出现在输出中。
+    pub fn new(exit_code: Option<i32>, crash_log: Option<CrashLog>, output: &str) -> CrashAnalysis {
+        let mut diagnoses = match &crash_log {
+            Some(log) => diagnose(&log.content),
+            None => vec![],
+        };
+
+        for diagnosis in diagnose(output) {
+            if !diagnoses.iter().any(|x| x.cause == diagnosis.cause) {
+                diagnoses.push(diagnosis);
+            }
+        }
+
+        CrashAnalysis {
+            exit_code,
+            crash_log,
+            diagnoses,
+        }
+    }
+
+    /// 供脚本使用的 JSON。
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    /// 把 JSON 写入文件，供脚本读取，会覆盖已有的文件。
+    ///
+    /// `path`: 通常为 `GameDirectory::crash_analysis`。
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = self.to_json()?;
+
+        if let Some(parent) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(parent) {
+                return Err(format!("{}: {}", parent.display(), err));
+            }
+        }
+
+        match std::fs::write(path, json) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+}
+
+impl fmt::Display for CrashAnalysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.exit_code {
+            Some(code) => writeln!(f, "游戏异常退出, 退出代码 {}", code)?,
+            None => writeln!(f, "游戏被强制结束")?,
+        }
+
+        if let Some(log) = &self.crash_log {
+            let kind = match log.kind {
+                CrashLogKind::CrashReport => "崩溃报告",
+                CrashLogKind::JvmCrash => "JVM 崩溃日志",
+            };
+            writeln!(f, "{}: {}", kind, log.path.display())?;
+
+            if let Some(description) = log
+                .sections
+                .first()
+                .and_then(|x| x.body.lines().find_map(|x| x.strip_prefix("Description: ")))
+            {
+                writeln!(f, "描述: {}", description)?;
+            }
+        }
+
+        if self.diagnoses.is_empty() {
+            return write!(f, "没有找到已知的原因, 请查看崩溃日志和游戏输出");
+        }
+
+        write!(f, "可能的原因:")?;
+        for diagnosis in &self.diagnoses {
+            write!(
+                f,
+                "\n- {}\n  ({})",
+                diagnosis.description, diagnosis.evidence
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 版本的游戏设置所在的文件名，位于版本文件夹中
+pub const INSTANCE_SETTINGS_FILE: &str = "launcher_instance.json";
+
+/// 游戏的根文件夹，即 `.minecraft`。
+///
+/// 所有版本、库文件、资源文件和启动器管理的 Java 都保存在这里，路径都是绝对路径，
+/// 不受启动器当前目录的影响。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameDirectory {
+    pub root: PathBuf,
+}
+
+impl Default for GameDirectory {
+    fn default() -> GameDirectory {
+        GameDirectory::new(default_root())
+    }
+}
+
+/// 当前系统上游戏的默认位置，与官方启动器相同。
+///
+/// Windows 上为 `%APPDATA%\.minecraft`，macOS 上为 `~/Library/Application Support/minecraft`；
+/// 其他系统上已经存在 `~/.minecraft` 时使用它，否则使用 `$XDG_DATA_HOME/minecraft`
+/// (默认为 `~/.local/share/minecraft`)。
+///
+/// 找不到用户目录时使用当前目录下的 `.minecraft`。
+pub fn default_root() -> PathBuf {
+    let env = |name: &str| {
+        std::env::var_os(name)
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+    };
+    let fallback = PathBuf::from(".minecraft");
+
+    if cfg!(windows) {
+        return env("APPDATA")
+            .map(|x| x.join(".minecraft"))
+            .unwrap_or(fallback);
+    }
+
+    let home = match env("HOME") {
+        Some(result) => result,
+        None => return fallback,
+    };
+
+    if cfg!(target_os = "macos") {
+        return home.join("Library/Application Support/minecraft");
+    }
+
+    let legacy = home.join(".minecraft");
+    if legacy.is_dir() {
+        return legacy;
+    }
+
+    env("XDG_DATA_HOME")
+        .unwrap_or_else(|| home.join(".local/share"))
+        .join("minecraft")
+}
+
+/// 一个版本使用的 `${game_directory}`，即模组、配置、存档和 `options.txt` 所在的文件夹。
+///
+/// 库文件、资源文件和 Java 运行时总是共享的。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum GameDirMode {
+    /// 与其他版本共用游戏的根文件夹
+    #[default]
+    Shared,
+    /// 使用版本自己的文件夹 `versions/<name>`
+    Isolated,
+    /// 使用指定的文件夹，相对路径相对于游戏的根文件夹
+    Custom { path: PathBuf },
+}
+
+impl GameDirectory {
+    /// `root`: 游戏的根文件夹，相对路径按当前目录转换为绝对路径。
+    pub fn new(root: impl Into<PathBuf>) -> GameDirectory {
+        let root = root.into();
+        let root = std::path::absolute(&root).unwrap_or(root);
+
+        GameDirectory { root }
+    }
+
+    /// `versions`
+    pub fn versions(&self) -> PathBuf {
+        self.root.join("versions")
+    }
+
+    /// `versions/<name>`
+    pub fn version_dir(&self, name: &str) -> PathBuf {
+        self.versions().join(name)
+    }
+
+    /// `versions/<name>/<name>.json`
+    pub fn version_json(&self, name: &str) -> PathBuf {
+        self.version_dir(name).join(format!("{}.json", name))
+    }
+
+    /// `versions/<name>/<name>.jar`
+    pub fn version_jar(&self, name: &str) -> PathBuf {
+        self.version_dir(name).join(format!("{}.jar", name))
+    }
+
+    /// 版本的游戏设置 `versions/<name>/launcher_instance.json`
+    pub fn instance_settings(&self, name: &str) -> PathBuf {
+        self.version_dir(name).join(INSTANCE_SETTINGS_FILE)
+    }
+
+    /// 一个版本实际使用的 `${game_directory}`。
+    ///
+    /// `name`: 版本名称。
+    ///
+    /// `mode`: 版本设置中的游戏文件夹。
+    pub fn game_dir(&self, name: &str, mode: &GameDirMode) -> PathBuf {
+        match mode {
+            GameDirMode::Shared => self.root.clone(),
+            GameDirMode::Isolated => self.version_dir(name),
+            GameDirMode::Custom { path } => self.root.join(path),
+        }
+    }
+
+    /// 本地库解压到的文件夹 `versions/<name>/natives`
+    pub fn natives(&self, name: &str) -> PathBuf {
+        self.version_dir(name).join("natives")
+    }
+
+    /// `libraries`
+    pub fn libraries(&self) -> PathBuf {
+        self.root.join("libraries")
+    }
+
+    /// `libraries/<path>`
+    ///
+    /// `path`: 库文件下载项中的 `path`。
+    pub fn library(&self, path: &str) -> PathBuf {
+        self.libraries().join(path)
+    }
+
+    /// `assets`
+    pub fn assets(&self) -> PathBuf {
+        self.root.join("assets")
+    }
+
+    /// `assets/indexes/<id>.json`
+    pub fn asset_index(&self, id: &str) -> PathBuf {
+        self.assets().join("indexes").join(format!("{}.json", id))
+    }
+
+    /// `assets/objects/<path>`
+    ///
+    /// `path`: 资源文件的 `hash` 的前两位和完整的 `hash`，见 `AssetObject::path`。
+    pub fn asset_object(&self, path: &str) -> PathBuf {
+        self.assets().join("objects").join(path)
+    }
+
+    /// 旧版本按文件名读取资源的文件夹 `assets/virtual/<id>`
+    pub fn virtual_assets(&self, id: &str) -> PathBuf {
+        self.assets().join("virtual").join(id)
+    }
+
+    /// 启动器管理的 Java 所在的文件夹 `runtime`
+    pub fn runtime(&self) -> PathBuf {
+        self.root.join("runtime")
+    }
+
+    /// `logs`
+    pub fn logs(&self) -> PathBuf {
+        self.root.join("logs")
+    }
+
+    /// 启动器保存游戏输出的文件 `logs/launcher/latest.log`
+    pub fn launcher_log(&self) -> PathBuf {
+        self.logs().join("launcher").join("latest.log")
+    }
+
+    /// 游戏最近一次崩溃的分析结果 `logs/launcher/crash-analysis.json`
+    pub fn crash_analysis(&self) -> PathBuf {
+        self.logs().join("launcher").join("crash-analysis.json")
+    }
+
+    /// `crash-reports`
+    pub fn crash_reports(&self) -> PathBuf {
+        self.root.join("crash-reports")
+    }
+
+    /// 账号文件 `launcher_accounts.json`
+    pub fn accounts(&self) -> PathBuf {
+        self.root.join("launcher_accounts.json")
+    }
+}
@@ -1,7 +1,13 @@
+#[allow(clippy::module_inception)]
 pub mod downloader {
-    use anyhow::Result;
+    use crate::verify::{check_file, FileStatus};
+    use anyhow::{anyhow, Result};
+    use indicatif::ProgressBar;
+    use std::collections::VecDeque;
     use std::num::{NonZeroU8, NonZeroUsize};
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use url::Url;
 
     use http_downloader::{
@@ -9,11 +15,61 @@ pub mod downloader {
         status_tracker::DownloadStatusTrackerExtension, HttpDownloaderBuilder,
     };
 
+    /// 一个下载任务。
+    #[derive(Debug, Clone)]
+    pub struct DownloadTask {
+        /// 保存的文件路径
+        pub path: String,
+        pub url: String,
+        /// 预期的 sha1，为 `None` 时不校验
+        pub sha1: Option<String>,
+        /// 预期的大小，为 `None` 时不校验
+        pub size: Option<u64>,
+    }
+
+    impl DownloadTask {
+        pub fn new(path: String, url: String) -> DownloadTask {
+            DownloadTask {
+                path,
+                url,
+                sha1: None,
+                size: None,
+            }
+        }
+
+        /// 设置下载完成后用于校验的 sha1 和大小。
+        pub fn checked(mut self, sha1: &str, size: u64) -> DownloadTask {
+            self.sha1 = Some(sha1.to_string());
+            self.size = Some(size);
+            self
+        }
+
+        /// 校验已经存在的文件。
+        pub fn check(&self) -> FileStatus {
+            check_file(Path::new(&self.path), self.sha1.as_deref(), self.size)
+        }
+    }
+
+    /// 下载文件。
+    ///
+    /// `path`: 保存的文件路径。
+    ///
+    /// `url`: 文件的地址。
     pub async fn download(path: &str, url: &str) -> Result<()> {
-        let save_dir = PathBuf::from(path);
+        let path = PathBuf::from(path);
+        let save_dir = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("无效的文件路径: {}", path.display()))?
+            .to_string_lossy()
+            .into_owned();
         let test_url = Url::parse(url)?;
         let (mut downloader, (status_state, speed_state)) =
             HttpDownloaderBuilder::new(test_url, save_dir)
+                .file_name(Some(file_name))
                 .chunk_size(NonZeroUsize::new(1024 * 1024 * 10).unwrap()) // 块大小
                 .download_connection_count(NonZeroU8::new(3).unwrap())
                 .build((
@@ -36,4 +92,130 @@ pub mod downloader {
         let _ = download_future.await?;
         Ok(())
     }
+
+    /// 默认的最大同时下载数
+    pub const DOWNLOAD_THREAD_MAX: usize = 64;
+
+    /// 默认的单个任务重试次数
+    pub const DOWNLOAD_RETRY_MAX: u32 = 3;
+
+    /// 下载失败的任务。
+    #[derive(Debug, Clone)]
+    pub struct DownloadFailure {
+        pub task: DownloadTask,
+        /// 最后一次失败的原因
+        pub reason: String,
+    }
+
+    /// 下载调度器，以有限的并发数下载一组任务。
+    ///
+    /// 每个任务下载后都会校验，校验失败或下载出错时按指数退避重试。
+    #[derive(Debug, Clone)]
+    pub struct Scheduler {
+        /// 最大同时下载数
+        pub concurrency: usize,
+        /// 单个任务失败后的重试次数
+        pub retries: u32,
+        /// 第一次重试前的等待时间，之后每次翻倍
+        pub backoff: Duration,
+    }
+
+    impl Default for Scheduler {
+        fn default() -> Self {
+            Scheduler::new()
+        }
+    }
+
+    impl Scheduler {
+        pub fn new() -> Scheduler {
+            Scheduler {
+                concurrency: DOWNLOAD_THREAD_MAX,
+                retries: DOWNLOAD_RETRY_MAX,
+                backoff: Duration::from_millis(500),
+            }
+        }
+
+        /// 下载所有任务。
+        ///
+        /// `tasks`: 要下载的任务。
+        ///
+        /// `progress_bar`: 每完成 (或最终失败) 一个任务前进 1。
+        ///
+        /// 返回: 所有重试后仍然失败的任务。下载时发生 panic 会传递给调用者。
+        pub async fn run(
+            &self,
+            tasks: Vec<DownloadTask>,
+            progress_bar: &ProgressBar,
+        ) -> Vec<DownloadFailure> {
+            let workers = self.concurrency.max(1).min(tasks.len());
+            let queue = Arc::new(Mutex::new(VecDeque::from(tasks)));
+            let failures = Arc::new(Mutex::new(vec![]));
+            let mut handles = vec![];
+
+            for _ in 0..workers {
+                let queue = queue.clone();
+                let failures = failures.clone();
+                let progress_bar = progress_bar.clone();
+                let scheduler = self.clone();
+
+                handles.push(tokio::spawn(async move {
+                    loop {
+                        // 只在取任务时持有锁
+                        let task = match queue.lock().unwrap().pop_front() {
+                            Some(task) => task,
+                            None => break,
+                        };
+
+                        if let Err(reason) = scheduler.run_task(&task).await {
+                            failures
+                                .lock()
+                                .unwrap()
+                                .push(DownloadFailure { task, reason });
+                        }
+
+                        progress_bar.inc(1);
+                    }
+                }));
+            }
+
+            for handle in handles {
+                // 工作任务 panic 时，它正在下载的任务会丢失，不能当作下载成功
+                if let Err(err) = handle.await {
+                    if let Ok(panic) = err.try_into_panic() {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            }
+
+            let failures = failures.lock().unwrap();
+            failures.clone()
+        }
+
+        /// 下载并校验一个任务，失败时重试。
+        async fn run_task(&self, task: &DownloadTask) -> Result<(), String> {
+            let mut reason = String::new();
+
+            for attempt in 0..=self.retries {
+                if attempt > 0 {
+                    let factor = 2u32.saturating_pow(attempt - 1);
+                    tokio::time::sleep(self.backoff.saturating_mul(factor)).await;
+                }
+
+                // 删除损坏或下载了一半的文件
+                if Path::new(&task.path).is_file() {
+                    let _ = std::fs::remove_file(&task.path);
+                }
+
+                reason = match download(&task.path, &task.url).await {
+                    Ok(_) => match task.check() {
+                        FileStatus::Valid => return Ok(()),
+                        status => status.to_string(),
+                    },
+                    Err(err) => err.to_string(),
+                };
+            }
+
+            Err(reason)
+        }
+    }
 }
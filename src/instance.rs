@@ -0,0 +1,561 @@
+use crate::command::JvmOptions;
+use crate::directory::{GameDirMode, GameDirectory};
+use crate::java::DEFAULT_JAVA_MAJOR_VERSION;
+use crate::minecraft_core::load_version;
+use crate::model::{GameVersionType, VersionJson};
+use crate::rules::Environment;
+use crate::write_to_file;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs::create_dir_all;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+/// 模组加载器的种类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoaderKind {
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+}
+
+impl fmt::Display for LoaderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LoaderKind::Forge => "Forge",
+            LoaderKind::NeoForge => "NeoForge",
+            LoaderKind::Fabric => "Fabric",
+            LoaderKind::Quilt => "Quilt",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 安装在版本中的模组加载器。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModLoader {
+    pub kind: LoaderKind,
+    /// 加载器的版本，例如 `0.14.21`
+    pub version: String,
+}
+
+impl fmt::Display for ModLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.version)
+    }
+}
+
+/// 游戏窗口的大小，对应 `--width` 和 `--height`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 一个游戏实例，即 `versions` 下的一个版本文件夹和它的设置。
+///
+/// 设置保存在版本文件夹中的 `launcher_instance.json`，没有设置文件的版本使用默认设置。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Instance {
+    /// 版本名称，即 `versions` 下的文件夹名，不保存在设置文件中
+    #[serde(skip)]
+    pub name: String,
+    /// 基于的游戏版本，例如 `1.19`
+    pub game_version: String,
+    /// 模组加载器，原版为 `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loader: Option<ModLoader>,
+    /// 指定使用的 java，为 `None` 时自动选择
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub java: Option<PathBuf>,
+    /// 初始内存，单位 MiB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_memory: Option<u64>,
+    /// 最大内存，单位 MiB，为 `None` 时按系统内存自动分配
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory: Option<u64>,
+    /// 额外的 jvm 参数
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub jvm_arguments: Vec<String>,
+    /// 游戏窗口的大小，为 `None` 时使用游戏的默认值
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowSize>,
+    /// 游戏文件夹，决定模组、配置和存档是否与其他版本隔离
+    pub game_directory: GameDirMode,
+    /// 图标，例如方块名称或图片文件的路径
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// 备注
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// 创建时间，Unix 时间戳，单位秒
+    pub created: u64,
+    /// 上次启动的时间，Unix 时间戳，单位秒
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_played: Option<u64>,
+}
+
+/// 当前时间的 Unix 时间戳。
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+/// 检查版本名称能否作为文件夹名。
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(String::from("版本名称不能为空"));
+    }
+
+    if name == "." || name == ".." || name.trim() != name {
+        return Err(format!("版本名称 {} 无效", name));
+    }
+
+    if let Some(x) = name
+        .chars()
+        .find(|x| x.is_control() || r#"/\:*?"<>|"#.contains(*x))
+    {
+        return Err(format!("版本名称不能包含 {:?}", x));
+    }
+
+    Ok(())
+}
+
+/// 读取 `versions/<name>/<name>.json`，保留所有字段。
+fn read_version_value(directory: &GameDirectory, name: &str) -> Result<Value, String> {
+    let path = directory.version_json(name);
+    let text = match std::fs::read_to_string(&path) {
+        Ok(result) => result,
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+
+    serde_json::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// 继承或使用 `name` 的 jar 的其他版本，这些版本依赖 `name` 的文件夹。
+pub fn dependents(directory: &GameDirectory, name: &str) -> Vec<String> {
+    let entries = match std::fs::read_dir(directory.versions()) {
+        Ok(result) => result,
+        Err(_) => return vec![],
+    };
+
+    let mut result: Vec<String> = entries
+        .filter_map(|x| x.ok())
+        .filter_map(|x| x.file_name().into_string().ok())
+        .filter(|x| x != name)
+        .filter(|x| match read_version_value(directory, x) {
+            Ok(value) => ["inheritsFrom", "jar"]
+                .iter()
+                .any(|key| value[key].as_str() == Some(name)),
+            Err(_) => false,
+        })
+        .collect();
+
+    result.sort();
+    result
+}
+
+/// 复制整个文件夹。
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    for entry in WalkDir::new(from) {
+        let entry = entry.map_err(|err| err.to_string())?;
+        let target = to.join(entry.path().strip_prefix(from).unwrap());
+
+        let result = if entry.file_type().is_dir() {
+            create_dir_all(&target)
+        } else {
+            std::fs::copy(entry.path(), &target).map(|_| ())
+        };
+
+        if let Err(err) = result {
+            return Err(format!("{}: {}", target.display(), err));
+        }
+    }
+
+    Ok(())
+}
+
+/// 把文件夹 `versions/<to>` 中以 `from` 命名的 json 和 jar 改为以 `to` 命名。
+///
+/// json 中的 `id` 与 `from` 相同时一起修改，其他字段保持不变。
+fn rename_version_files(directory: &GameDirectory, from: &str, to: &str) -> Result<(), String> {
+    let dir = directory.version_dir(to);
+
+    let jar = dir.join(format!("{}.jar", from));
+    if jar.is_file() {
+        if let Err(err) = std::fs::rename(&jar, directory.version_jar(to)) {
+            return Err(format!("{}: {}", jar.display(), err));
+        }
+    }
+
+    let json = dir.join(format!("{}.json", from));
+    if !json.is_file() {
+        return Ok(());
+    }
+
+    let mut value: Value = match std::fs::read_to_string(&json) {
+        Ok(result) => {
+            serde_json::from_str(&result).map_err(|err| format!("{}: {}", json.display(), err))?
+        }
+        Err(err) => return Err(format!("{}: {}", json.display(), err)),
+    };
+
+    if value["id"].as_str() == Some(from) {
+        value["id"] = Value::from(to);
+    }
+
+    if let Err(err) = std::fs::remove_file(&json) {
+        return Err(format!("{}: {}", json.display(), err));
+    }
+    write_to_file(
+        &directory.version_json(to).to_string_lossy(),
+        serde_json::to_string(&value).unwrap().as_bytes(),
+    );
+
+    Ok(())
+}
+
+impl Instance {
+    /// `name`: 版本名称，即 `versions` 下的文件夹名。
+    ///
+    /// `game_version`: 基于的游戏版本。
+    pub fn new(name: &str, game_version: &str) -> Instance {
+        Instance {
+            name: name.to_string(),
+            game_version: game_version.to_string(),
+            created: now(),
+            ..Instance::default()
+        }
+    }
+
+    /// 读取一个版本的设置，设置文件不存在时返回默认设置。
+    ///
+    /// 没有设置文件时，游戏版本取自版本 json 继承的最顶层的版本。
+    ///
+    /// `name`: 版本名称，即 `versions` 下的文件夹名。
+    pub fn load(directory: &GameDirectory, name: &str) -> Result<Instance, String> {
+        let path = directory.instance_settings(name);
+
+        if !path.is_file() {
+            let mut game_version = name.to_string();
+            let mut visited = vec![];
+
+            while let Ok(value) = read_version_value(directory, &game_version) {
+                let parent = match value["inheritsFrom"].as_str() {
+                    Some(result) if !visited.contains(&result.to_string()) => result,
+                    _ => {
+                        if let Some(id) = value["id"].as_str() {
+                            game_version = id.to_string();
+                        }
+                        break;
+                    }
+                };
+
+                visited.push(game_version);
+                game_version = parent.to_string();
+            }
+
+            return Ok(Instance {
+                name: name.to_string(),
+                game_version,
+                ..Instance::default()
+            });
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .map_err(|err| format!("读取版本设置 {} 时发生错误: {}", path.display(), err))?;
+        let mut instance: Instance = serde_json::from_str(&text)
+            .map_err(|err| format!("解析版本设置 {} 时发生错误: {}", path.display(), err))?;
+        instance.name = name.to_string();
+        Ok(instance)
+    }
+
+    /// 保存到版本文件夹中。
+    pub fn save(&self, directory: &GameDirectory) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|err| err.to_string())?;
+
+        write_to_file(
+            &directory.instance_settings(&self.name).to_string_lossy(),
+            text.as_bytes(),
+        );
+        Ok(())
+    }
+
+    /// 为一个已经安装的版本创建实例设置。
+    ///
+    /// `name`: 版本名称，版本 json 必须已经存在。
+    ///
+    /// `game_version`: 基于的游戏版本。
+    pub fn create(
+        directory: &GameDirectory,
+        name: &str,
+        game_version: &str,
+    ) -> Result<Instance, String> {
+        validate_name(name)?;
+
+        if !directory.version_json(name).is_file() {
+            return Err(format!("版本 {} 还没有安装", name));
+        }
+
+        if directory.instance_settings(name).exists() {
+            return Err(format!("版本 {} 已经有设置文件", name));
+        }
+
+        let instance = Instance::new(name, game_version);
+        instance.save(directory)?;
+        Ok(instance)
+    }
+
+    /// 复制整个版本文件夹，包括隔离的游戏文件夹中的模组、配置和存档。
+    ///
+    /// `name`: 新的版本名称，不能已经存在。
+    ///
+    /// 返回: 新的实例，创建时间为现在，没有启动过。
+    pub fn clone_to(&self, directory: &GameDirectory, name: &str) -> Result<Instance, String> {
+        validate_name(name)?;
+
+        let target = directory.version_dir(name);
+        if target.exists() {
+            return Err(format!("版本 {} 已经存在", name));
+        }
+
+        copy_dir(&directory.version_dir(&self.name), &target)?;
+        rename_version_files(directory, &self.name, name)?;
+
+        let instance = Instance {
+            name: name.to_string(),
+            created: now(),
+            last_played: None,
+            ..self.clone()
+        };
+        instance.save(directory)?;
+        Ok(instance)
+    }
+
+    /// 重命名版本文件夹和其中的 json、jar。
+    ///
+    /// 被其他版本继承的版本不能重命名，否则那些版本无法启动。
+    ///
+    /// `name`: 新的版本名称，不能已经存在。
+    pub fn rename(&mut self, directory: &GameDirectory, name: &str) -> Result<(), String> {
+        validate_name(name)?;
+
+        let target = directory.version_dir(name);
+        if target.exists() {
+            return Err(format!("版本 {} 已经存在", name));
+        }
+
+        let dependents = dependents(directory, &self.name);
+        if !dependents.is_empty() {
+            return Err(format!(
+                "版本 {} 被 {} 使用, 不能重命名",
+                self.name,
+                dependents.join(", ")
+            ));
+        }
+
+        let source = directory.version_dir(&self.name);
+        if let Err(err) = std::fs::rename(&source, &target) {
+            return Err(format!("{}: {}", source.display(), err));
+        }
+        rename_version_files(directory, &self.name, name)?;
+
+        self.name = name.to_string();
+        self.save(directory)
+    }
+
+    /// 删除整个版本文件夹，包括隔离的游戏文件夹中的存档。
+    ///
+    /// 被其他版本继承的版本不能删除。
+    pub fn delete(self, directory: &GameDirectory) -> Result<(), String> {
+        let dependents = dependents(directory, &self.name);
+        if !dependents.is_empty() {
+            return Err(format!(
+                "版本 {} 被 {} 使用, 不能删除",
+                self.name,
+                dependents.join(", ")
+            ));
+        }
+
+        let path = directory.version_dir(&self.name);
+        std::fs::remove_dir_all(&path).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// 记录启动时间并保存。
+    pub fn mark_played(&mut self, directory: &GameDirectory) -> Result<(), String> {
+        self.last_played = Some(now());
+        self.save(directory)
+    }
+
+    /// 这个实例的内存和 jvm 参数，没有指定最大内存时自动分配。
+    pub fn jvm_options(&self) -> JvmOptions {
+        JvmOptions {
+            min_memory: self.min_memory,
+            max_memory: self.max_memory,
+            auto_memory: true,
+            extra_arguments: self.jvm_arguments.clone(),
+        }
+    }
+}
+
+/// 按版本 json 中的库文件识别模组加载器。
+///
+/// `version`: 合并继承的版本之后的版本 json。
+pub fn detect_loader(version: &VersionJson) -> Option<ModLoader> {
+    version.libraries.iter().find_map(|library| {
+        let mut parts = library.name.split(':');
+        let (group, artifact, version) = (parts.next()?, parts.next()?, parts.next()?);
+
+        let kind = match (group, artifact) {
+            ("net.fabricmc", "fabric-loader") => LoaderKind::Fabric,
+            ("org.quiltmc", "quilt-loader") => LoaderKind::Quilt,
+            ("net.minecraftforge", "forge") => LoaderKind::Forge,
+            ("net.neoforged", "neoforge" | "forge") => LoaderKind::NeoForge,
+            _ => return None,
+        };
+
+        // Forge 的版本号以游戏版本开头，例如 `1.19-41.1.0`
+        let version = match kind {
+            LoaderKind::Forge | LoaderKind::NeoForge => {
+                version.split_once('-').map(|x| x.1).unwrap_or(version)
+            }
+            _ => version,
+        };
+
+        Some(ModLoader {
+            kind,
+            version: version.to_string(),
+        })
+    })
+}
+
+/// 一个已经安装的版本是否可以启动。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionStatus {
+    Complete,
+    /// 缺少游戏主文件、库文件或资源索引，校验并修复后可以启动
+    Incomplete {
+        missing: Vec<PathBuf>,
+    },
+    /// 版本 json 或设置文件无法读取，或继承的版本不存在
+    Broken(String),
+}
+
+/// `installed_versions` 找到的一个版本。
+#[derive(Debug, Clone)]
+pub struct InstalledVersion {
+    pub instance: Instance,
+    /// 版本损坏时为 `None`
+    pub version_type: Option<GameVersionType>,
+    /// 设置中的模组加载器，没有设置时按库文件识别
+    pub loader: Option<ModLoader>,
+    /// 需要的 Java 主版本号，版本损坏时为 `None`
+    pub java_version: Option<u32>,
+    pub status: VersionStatus,
+}
+
+/// 检查启动需要的文件是否存在，不校验内容。
+fn missing_files(directory: &GameDirectory, name: &str, version: &VersionJson) -> Vec<PathBuf> {
+    let env = Environment::current();
+
+    let mut files = vec![directory.version_jar(version.jar.as_deref().unwrap_or(name))];
+
+    for library in version.libraries.iter().filter(|x| x.is_allowed(&env)) {
+        if let Some(file) = library.file() {
+            files.push(directory.library(&file.path));
+        }
+
+        if let Some(classifier) = library
+            .native_classifier(&env)
+            .and_then(|x| library.classifier(&x))
+        {
+            files.push(directory.library(&classifier.path));
+        }
+    }
+
+    // 与下载时相同，资源索引按 `assets` 命名
+    if let Some(assets) = &version.assets {
+        files.push(directory.asset_index(assets));
+    }
+
+    files.retain(|x| !x.is_file());
+    files
+}
+
+/// 查看 `versions` 中的一个版本。
+///
+/// `name`: 版本名称，即 `versions` 下的文件夹名。
+pub fn inspect_version(directory: &GameDirectory, name: &str) -> InstalledVersion {
+    let mut result = InstalledVersion {
+        instance: Instance {
+            name: name.to_string(),
+            game_version: name.to_string(),
+            ..Instance::default()
+        },
+        version_type: None,
+        loader: None,
+        java_version: None,
+        status: VersionStatus::Complete,
+    };
+
+    match Instance::load(directory, name) {
+        Ok(instance) => result.instance = instance,
+        Err(err) => {
+            result.status = VersionStatus::Broken(err);
+            return result;
+        }
+    }
+    result.loader = result.instance.loader.clone();
+
+    let version = match load_version(directory, name) {
+        Ok(version) => version,
+        Err(err) => {
+            result.status = VersionStatus::Broken(err);
+            return result;
+        }
+    };
+
+    result.version_type = Some(version.version_type);
+    result.loader = result.loader.or_else(|| detect_loader(&version));
+    result.java_version = Some(
+        version
+            .java_version
+            .as_ref()
+            .map(|x| x.major_version)
+            .unwrap_or(DEFAULT_JAVA_MAJOR_VERSION),
+    );
+
+    let missing = missing_files(directory, name, &version);
+    if !missing.is_empty() {
+        result.status = VersionStatus::Incomplete { missing };
+    }
+
+    result
+}
+
+/// 列出 `versions` 中的所有版本，按名称排序。
+pub fn installed_versions(directory: &GameDirectory) -> Vec<InstalledVersion> {
+    let entries = match std::fs::read_dir(directory.versions()) {
+        Ok(result) => result,
+        Err(_) => return vec![],
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|x| x.ok())
+        .filter(|x| x.path().is_dir())
+        .filter_map(|x| x.file_name().into_string().ok())
+        .collect();
+    names.sort();
+
+    names
+        .iter()
+        .map(|name| inspect_version(directory, name))
+        .collect()
+}
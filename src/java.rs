@@ -0,0 +1,266 @@
+use crate::model::JavaVersion;
+use crate::rules::Environment;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// 没有 `javaVersion` 的旧版本需要的 Java 版本
+pub const DEFAULT_JAVA_MAJOR_VERSION: u32 = 8;
+
+/// 要求 Java 8 及以下的版本只能使用比这个版本低的 Java，
+/// 旧版本的游戏和 LaunchWrapper 在 Java 9 及以上无法启动
+pub const LEGACY_JAVA_MAJOR_VERSION_LIMIT: u32 = 9;
+
+/// 一个可用的 Java。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaRuntime {
+    /// java 可执行文件
+    pub path: PathBuf,
+    /// `java.version`，例如 `1.8.0_382` 或 `17.0.8`
+    pub version: String,
+    /// 主版本号，例如 `8` 或 `17`
+    pub major_version: u32,
+    /// 与 `Environment::os_arch` 相同的架构名称
+    pub arch: String,
+}
+
+/// java 可执行文件的文件名。
+fn java_executable() -> &'static str {
+    if cfg!(windows) {
+        "java.exe"
+    } else {
+        "java"
+    }
+}
+
+/// Mojang Java 运行时索引中当前平台的名称。
+///
+/// 返回: 没有对应的运行时的平台返回 `None`。
+pub fn runtime_platform(env: &Environment) -> Option<&'static str> {
+    let platform = match (env.os_name.as_str(), env.os_arch.as_str()) {
+        ("linux", "x86_64") => "linux",
+        ("linux", "x86") => "linux-i386",
+        ("osx", "x86_64") => "mac-os",
+        ("osx", "arm64") => "mac-os-arm64",
+        ("windows", "x86_64") => "windows-x64",
+        ("windows", "x86") => "windows-x86",
+        ("windows", "arm64") => "windows-arm64",
+        _ => return None,
+    };
+
+    Some(platform)
+}
+
+/// 从 `java.version` 中取出主版本号。
+///
+/// `1.8.0_382` 为 8，`17.0.8` 为 17，`21` 为 21。
+pub fn major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|x: char| !x.is_ascii_digit());
+    let first: u32 = parts.next()?.parse().ok()?;
+
+    if first == 1 {
+        parts.next()?.parse().ok()
+    } else {
+        Some(first)
+    }
+}
+
+/// 把 `os.arch` 转换为与 `Environment::os_arch` 相同的名称。
+fn normalize_arch(arch: &str) -> String {
+    match arch {
+        "amd64" | "x86_64" => String::from("x86_64"),
+        "x86" | "i386" | "i486" | "i586" | "i686" => String::from("x86"),
+        "aarch64" | "arm64" => String::from("arm64"),
+        "arm" | "aarch32" => String::from("arm32"),
+        other => other.to_string(),
+    }
+}
+
+/// 解析 `java -XshowSettings:properties -version` 的输出。
+///
+/// 返回: `(java.version, 架构)`。
+pub fn parse_properties(output: &str) -> Option<(String, String)> {
+    let mut version = None;
+    let mut arch = None;
+
+    for line in output.lines() {
+        let (name, value) = match line.split_once('=') {
+            Some(result) => result,
+            None => continue,
+        };
+
+        match name.trim() {
+            "java.version" => version = Some(value.trim().to_string()),
+            "os.arch" => arch = Some(normalize_arch(value.trim())),
+            _ => {}
+        }
+    }
+
+    Some((version?, arch?))
+}
+
+/// 运行一个 java 获取它的版本和架构。
+///
+/// 返回: 无法运行或无法解析输出时返回 `None`。
+pub fn probe(path: &Path) -> Option<JavaRuntime> {
+    let output = Command::new(path)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+
+    // 属性输出在标准错误中
+    let text = String::from_utf8_lossy(&output.stderr);
+    let (version, arch) = parse_properties(&text)?;
+
+    Some(JavaRuntime {
+        path: path.to_path_buf(),
+        major_version: major_version(&version)?,
+        version,
+        arch,
+    })
+}
+
+/// `dir` 下的所有子文件夹。
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(result) => result,
+        Err(_) => return vec![],
+    };
+
+    entries
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .filter(|x| x.is_dir())
+        .collect()
+}
+
+/// `dir` 下每个子文件夹中的 `bin/java`。
+///
+/// `home`: java home 在子文件夹中的相对路径，例如 macOS 上的 `Contents/Home`。
+fn java_in_subdirs(dir: &Path, home: &str) -> Vec<PathBuf> {
+    subdirs(dir)
+        .into_iter()
+        .map(|x| x.join(home).join("bin").join(java_executable()))
+        .collect()
+}
+
+/// 启动器管理的 Java，位于 `runtime/<组件>/<平台>/<组件>/bin/java`，
+/// macOS 上为 `.../<组件>/jre.bundle/Contents/Home/bin/java`。
+fn managed_runtimes(runtime_dir: &Path) -> Vec<PathBuf> {
+    let mut result = vec![];
+
+    for component in subdirs(runtime_dir) {
+        for platform in subdirs(&component) {
+            result.extend(java_in_subdirs(&platform, ""));
+            result.extend(java_in_subdirs(&platform, "jre.bundle/Contents/Home"));
+        }
+    }
+
+    result
+}
+
+/// 可能存在 java 的路径，不检查是否存在。
+///
+/// 依次为 `JAVA_HOME`、`PATH`、系统中常见的安装位置和启动器管理的 Java。
+///
+/// `runtime_dir`: 启动器管理的 Java 所在的文件夹，见 `GameDirectory::runtime`。
+pub fn candidates(runtime_dir: &Path) -> Vec<PathBuf> {
+    let mut result = vec![];
+
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        result.push(PathBuf::from(home).join("bin").join(java_executable()));
+    }
+
+    if let Some(path) = std::env::var_os("PATH") {
+        result.extend(std::env::split_paths(&path).map(|x| x.join(java_executable())));
+    }
+
+    if cfg!(windows) {
+        for variable in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = std::env::var_os(variable) {
+                let dir = PathBuf::from(dir);
+                for vendor in ["Java", "Eclipse Adoptium", "Zulu", "Microsoft", "BellSoft"] {
+                    result.extend(java_in_subdirs(&dir.join(vendor), ""));
+                }
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        result.extend(java_in_subdirs(
+            Path::new("/Library/Java/JavaVirtualMachines"),
+            "Contents/Home",
+        ));
+    } else {
+        for dir in [
+            "/usr/lib/jvm",
+            "/usr/lib64/jvm",
+            "/usr/java",
+            "/opt/java",
+            "/opt/jdk",
+        ] {
+            result.extend(java_in_subdirs(Path::new(dir), ""));
+        }
+    }
+
+    result.extend(managed_runtimes(runtime_dir));
+    result
+}
+
+/// 找出系统中所有可用的 Java。
+///
+/// 同一个 java 通过不同路径 (例如符号链接) 找到时只保留第一个。
+///
+/// `runtime_dir`: 启动器管理的 Java 所在的文件夹，见 `GameDirectory::runtime`。
+pub fn discover(runtime_dir: &Path) -> Vec<JavaRuntime> {
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+
+    for path in candidates(runtime_dir) {
+        if !path.is_file() {
+            continue;
+        }
+
+        let real = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if !seen.insert(real) {
+            continue;
+        }
+
+        if let Some(runtime) = probe(&path) {
+            result.push(runtime);
+        }
+    }
+
+    result
+}
+
+/// 从已有的 Java 中选择一个适合指定版本的。
+///
+/// 主版本号相同的优先，其次是比要求更高的版本中最低的一个；
+/// 同一主版本中与当前系统架构相同的优先。
+/// 要求 Java 8 及以下时不会选择 Java 9 及以上的版本，此时返回 `None`，
+/// 应当下载 `jre-legacy`。
+///
+/// `java_version`: 版本 json 中的 `javaVersion`，没有时按 Java 8 处理。
+pub fn select<'a>(
+    runtimes: &'a [JavaRuntime],
+    java_version: Option<&JavaVersion>,
+    env: &Environment,
+) -> Option<&'a JavaRuntime> {
+    let required = java_version
+        .map(|x| x.major_version)
+        .unwrap_or(DEFAULT_JAVA_MAJOR_VERSION);
+
+    runtimes
+        .iter()
+        .filter(|x| x.major_version >= required)
+        .filter(|x| {
+            required >= LEGACY_JAVA_MAJOR_VERSION_LIMIT
+                || x.major_version < LEGACY_JAVA_MAJOR_VERSION_LIMIT
+        })
+        .min_by_key(|x| {
+            (
+                x.major_version != required,
+                x.major_version,
+                x.arch != env.os_arch,
+            )
+        })
+}
@@ -1,14 +1,29 @@
 use chrono::{DateTime, Local};
 use colored::Colorize;
+use md5::{Digest, Md5};
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::str;
 use uuid::Uuid;
 use zip::read::ZipArchive;
 
-pub mod post;
+pub mod account;
+pub mod auth;
+pub mod command;
+pub mod crash;
+pub mod directory;
 pub mod downloader;
+pub mod instance;
+pub mod java;
+pub mod log4j;
 pub mod minecraft_core;
+pub mod model;
+pub mod post;
+pub mod process;
+pub mod rules;
+pub mod secret;
+pub mod verify;
+pub mod yggdrasil;
 
 /// 向指定的文件写入指定的内容。
 ///
@@ -18,15 +33,13 @@ pub mod minecraft_core;
 ///
 /// ## examples
 ///
-/// ```rust
-/// use minecraft::write_to_file;
+/// ```rust,no_run
+/// use command_minecraft_launcher::write_to_file;
 /// write_to_file("./.minecraft/assets/indexes/1.19.json", b"{}");
 /// ```
-///
-
 pub fn write_to_file(path: &str, contents: &[u8]) {
     let _path = Path::new(path);
-    let result = std::fs::create_dir_all(_path.parent().unwrap_or_else(|| _path));
+    let result = std::fs::create_dir_all(_path.parent().unwrap_or(_path));
     match result {
         Ok(_) => {}
         Err(err) => {
@@ -44,24 +57,20 @@ pub fn write_to_file(path: &str, contents: &[u8]) {
 ///
 /// ## Example
 ///
-/// ```rust
-/// use minecraft::get_path;
+/// ```rust,no_run
+/// use command_minecraft_launcher::get_path;
 /// println!("{}", get_path("./.minecraft").display().to_string());
 /// ```
-///
-
 pub fn get_path(path: &str) -> PathBuf {
     let canonicalized_path = std::fs::canonicalize(path).unwrap();
-    let stripped_path = strip_long_path_prefix(&canonicalized_path);
-    PathBuf::from(stripped_path)
+    strip_long_path_prefix(&canonicalized_path)
 }
 
-fn strip_long_path_prefix(path: &Path) -> String {
-    let path_string = path.to_string_lossy().into_owned();
-    if let Some(stripped) = path_string.strip_prefix(r"\\?\") {
-        stripped.to_owned()
-    } else {
-        path_string
+fn strip_long_path_prefix(path: &Path) -> PathBuf {
+    // 只处理能转换为 UTF-8 的路径，其他路径保持原样以免丢失字符
+    match path.to_str().and_then(|x| x.strip_prefix(r"\\?\")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => path.to_path_buf(),
     }
 }
 
@@ -76,14 +85,23 @@ pub fn info(info: &str) {
 ///
 /// `target`: 目标路径。
 ///
-
-fn extract(file: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+/// `exclude`: 不解压的路径前缀，即库的 `extract.exclude`，例如 `META-INF/`。
+fn extract(
+    file: &Path,
+    target: &Path,
+    exclude: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
     let file = File::open(file)?;
     let mut archive = ZipArchive::new(file)?;
 
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)?;
         let file_path = file.name().to_owned();
+
+        if exclude.iter().any(|x| file_path.starts_with(x.as_str())) {
+            continue;
+        }
+
         let target_path = target.join(&file_path);
 
         if let Some(parent) = target_path.parent() {
@@ -108,9 +126,49 @@ fn extract(file: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
+/// 旧版本使用的离线 UUID: OID 命名空间下的 UUID v5。
+///
+/// 与原版服务器不兼容，仅用于继续使用旧的存档。
 pub fn generate_uuid_without_hyphens(input: &str) -> String {
-    let uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, &input.as_bytes());
+    let uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, input.as_bytes());
     let uuid_string = uuid.to_string();
-    let uuid_without_hyphens = uuid_string.replace("-", "");
-    uuid_without_hyphens
+    uuid_string.replace('-', "")
+}
+
+/// 与原版相同的离线 UUID，即 Java 的 `UUID.nameUUIDFromBytes("OfflinePlayer:<name>")`。
+///
+/// `name`: 玩家名。
+///
+/// 返回: 不带连字符的 UUID。
+///
+/// ## Example
+///
+/// ```rust
+/// use command_minecraft_launcher::offline_uuid;
+/// assert_eq!(offline_uuid("Notch"), "b50ad385829d3141a2167e7d7539ba7f");
+/// ```
+pub fn offline_uuid(name: &str) -> String {
+    let hash = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes());
+    let uuid = uuid::Builder::from_md5_bytes(hash.into()).into_uuid();
+    uuid.simple().to_string()
+}
+
+/// 生成离线 UUID 的方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OfflineUuidScheme {
+    /// 与原版相同，见 `offline_uuid`
+    #[default]
+    Vanilla,
+    /// 旧版本使用的方式，见 `generate_uuid_without_hyphens`
+    Legacy,
+}
+
+impl OfflineUuidScheme {
+    /// 生成不带连字符的 UUID。
+    pub fn uuid(&self, name: &str) -> String {
+        match self {
+            OfflineUuidScheme::Vanilla => offline_uuid(name),
+            OfflineUuidScheme::Legacy => generate_uuid_without_hyphens(name),
+        }
+    }
 }
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Local, TimeZone};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fmt;
+
+/// log4j 的日志级别，按严重程度排序。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    /// 解析 `level` 属性，不区分大小写。
+    pub fn parse(level: &str) -> Option<LogLevel> {
+        match level.to_ascii_uppercase().as_str() {
+            "TRACE" => Some(LogLevel::Trace),
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            "FATAL" => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// 一条日志。
+///
+/// 不是 log4j 事件的输出 (例如 log4j 初始化之前的输出) 只有 `message`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub time: Option<DateTime<Local>>,
+    pub level: Option<LogLevel>,
+    pub thread: String,
+    pub logger: String,
+    pub message: String,
+    /// 异常的堆栈
+    pub throwable: Option<String>,
+}
+
+impl LogRecord {
+    /// 不是 log4j 事件的一行输出。
+    pub fn plain(line: &str) -> LogRecord {
+        LogRecord {
+            time: None,
+            level: None,
+            thread: String::new(),
+            logger: String::new(),
+            message: line.to_string(),
+            throwable: None,
+        }
+    }
+
+    /// 判断是否应该显示这条日志。
+    ///
+    /// `level`: 最低的日志级别，没有级别的日志总是满足。
+    ///
+    /// `keyword`: 消息、异常或 logger 中包含的文字，为空时不过滤。
+    pub fn matches(&self, level: LogLevel, keyword: &str) -> bool {
+        if self.level.map(|x| x < level).unwrap_or(false) {
+            return false;
+        }
+
+        keyword.is_empty()
+            || self.message.contains(keyword)
+            || self.logger.contains(keyword)
+            || self
+                .throwable
+                .as_deref()
+                .map(|x| x.contains(keyword))
+                .unwrap_or(false)
+    }
+}
+
+impl fmt::Display for LogRecord {
+    /// 与游戏默认的控制台格式相似，例如 `[12:00:00] [Render thread/INFO]: Setting user: Steve`。
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(time) = &self.time {
+            write!(f, "[{}] ", time.format("%H:%M:%S"))?;
+        }
+
+        if let Some(level) = &self.level {
+            write!(f, "[{}/{}]: ", self.thread, level)?;
+        }
+
+        write!(f, "{}", self.message)?;
+
+        if let Some(throwable) = &self.throwable {
+            write!(f, "\n{}", throwable)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 把 log4j `XMLLayout` 输出的事件逐行组合成日志。
+///
+/// 一个事件通常跨越多行，读到 `</log4j:Event>` 后才会得到对应的日志。
+#[derive(Debug, Clone, Default)]
+pub struct Log4jParser {
+    buffer: String,
+}
+
+impl Log4jParser {
+    pub fn new() -> Log4jParser {
+        Log4jParser::default()
+    }
+
+    /// 读入一行输出。
+    ///
+    /// 返回: 组成了一条完整的日志时返回它，事件还没有结束时返回 `None`。
+    pub fn push(&mut self, line: &str) -> Option<LogRecord> {
+        if self.buffer.is_empty() && !line.trim_start().starts_with("<log4j:Event") {
+            return Some(LogRecord::plain(line));
+        }
+
+        self.buffer.push_str(line);
+        self.buffer.push('\n');
+
+        if !line.contains("</log4j:Event>") {
+            return None;
+        }
+
+        let event = std::mem::take(&mut self.buffer);
+        // 无法解析的事件按原样显示
+        Some(parse_event(&event).unwrap_or_else(|| LogRecord::plain(event.trim_end())))
+    }
+
+    /// 游戏退出后取出没有结束的事件。
+    pub fn finish(&mut self) -> Option<LogRecord> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        let event = std::mem::take(&mut self.buffer);
+        Some(LogRecord::plain(event.trim_end()))
+    }
+}
+
+/// 解析一个完整的 `<log4j:Event>`。
+pub fn parse_event(xml: &str) -> Option<LogRecord> {
+    let mut reader = Reader::from_str(xml);
+    let mut record = LogRecord::plain("");
+    // 正在读取的子元素
+    let mut current: Option<Vec<u8>> = None;
+
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.name().as_ref() == b"log4j:Event" =>
+            {
+                for attribute in element.attributes() {
+                    let attribute = attribute.ok()?;
+                    let value = attribute.unescape_value().ok()?.into_owned();
+
+                    match attribute.key.as_ref() {
+                        b"logger" => record.logger = value,
+                        b"thread" => record.thread = value,
+                        b"level" => record.level = LogLevel::parse(&value),
+                        b"timestamp" => {
+                            record.time = value
+                                .parse()
+                                .ok()
+                                .and_then(|x| Local.timestamp_millis_opt(x).single())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::Start(element) => current = Some(element.name().as_ref().to_vec()),
+            Event::End(element) => {
+                if element.name().as_ref() == b"log4j:Event" {
+                    record.throwable = record.throwable.map(|x| x.trim_end().to_string());
+                    return Some(record);
+                }
+                current = None;
+            }
+            Event::CData(data) => {
+                let text = String::from_utf8_lossy(&data.into_inner()).into_owned();
+                append_text(&mut record, current.as_deref(), &text);
+            }
+            Event::Text(text) => {
+                let text = text.unescape().ok()?;
+                append_text(&mut record, current.as_deref(), &text);
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// 把子元素中的文字加到日志中，元素外的空白被忽略。
+fn append_text(record: &mut LogRecord, element: Option<&[u8]>, text: &str) {
+    match element {
+        Some(b"log4j:Message") => record.message.push_str(text),
+        Some(b"log4j:Throwable") => record
+            .throwable
+            .get_or_insert_with(String::new)
+            .push_str(text),
+        _ => {}
+    }
+}
@@ -1,8 +1,16 @@
-use colored::Colorize;
 use command_minecraft_launcher::{
-    generate_uuid_without_hyphens,
+    account::{Account, AccountStore},
+    auth::{request_device_code, AuthEndpoints, LoginError},
+    command,
+    crash::{find_crash_log, CrashAnalysis},
+    directory::{GameDirMode, GameDirectory},
+    instance::{installed_versions, InstalledVersion, Instance, VersionStatus, WindowSize},
+    log4j::{Log4jParser, LogLevel, LogRecord},
     minecraft_core::{DownloadManager, GameVersion, LaunchInfo, Launcher, Login},
-    post::Post,
+    process::{GameProcess, LogSource},
+    secret::{keyring_available, Protection, ProtectionKind},
+    yggdrasil::{resolve_api_root, AuthlibInjector, YggdrasilAccount},
+    OfflineUuidScheme,
 };
 use cursive::{
     view::{Nameable, Resizable, Scrollable},
@@ -11,13 +19,25 @@ use cursive::{
 };
 use lazy_static::lazy_static;
 use regex::Regex;
-use reqwest::header::HeaderMap;
-use serde_json::{json, Value};
-use std::{collections::HashMap, sync::Mutex};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// 分析崩溃原因时使用的游戏输出的行数
+const GAME_OUTPUT_TAIL: usize = 2000;
 
 lazy_static! {
     static ref NIGANMA: Mutex<u32> = Mutex::new(42);
-    static ref PLAYER_NAME: Mutex<String> = Mutex::new(String::new());
+    static ref GAME_DIR: GameDirectory = GameDirectory::default();
+    static ref ACCOUNTS: Mutex<AccountStore> =
+        Mutex::new(AccountStore::new(&accounts_path(), Protection::Keyring));
+}
+
+/// 账号文件的路径。
+fn accounts_path() -> String {
+    GAME_DIR.accounts().to_string_lossy().into_owned()
 }
 
 const LOGIN_SUPER_LINK: &str = "https://login.live.com/oauth20_authorize.srf?client_id=00000000402b5328&response_type=code&scope=service%3A%3Auser.auth.xboxlive.com%3A%3AMBI_SSL&redirect_uri=https%3A%2F%2Flogin.live.com%2Foauth20_desktop.srf";
@@ -51,28 +71,1006 @@ fn dialog_quit() -> Dialog {
         })
 }
 
+/// 读取账号文件，需要时询问密码。
+fn open_accounts(siv: &mut Cursive) {
+    match AccountStore::stored_protection(&accounts_path()) {
+        Ok(Some(ProtectionKind::Passphrase)) => siv.add_layer(dialog_passphrase(false)),
+        Ok(Some(ProtectionKind::Keyring)) => {
+            load_accounts(siv, Protection::Keyring);
+        }
+        Ok(Some(ProtectionKind::Plaintext)) => {
+            load_accounts(siv, Protection::Plaintext);
+        }
+        // 新建或迁移旧的明文账号文件，优先使用系统密钥环
+        Ok(Some(ProtectionKind::Legacy)) | Ok(None) => {
+            if keyring_available() {
+                load_accounts(siv, Protection::Keyring);
+            } else {
+                siv.add_layer(dialog_passphrase(true));
+            }
+        }
+        Err(err) => siv.add_layer(dialog_error(&err)),
+    }
+}
+
+/// 返回: 是否读取成功。
+fn load_accounts(siv: &mut Cursive, protection: Protection) -> bool {
+    match AccountStore::load(&accounts_path(), protection) {
+        Ok(accounts) => {
+            (*ACCOUNTS.lock().unwrap()) = accounts;
+            show_selected_account(siv);
+            true
+        }
+        Err(err) => {
+            siv.add_layer(dialog_error(&err));
+            false
+        }
+    }
+}
+
+/// `create`: 是否是第一次设置密码。
+fn dialog_passphrase(create: bool) -> Dialog {
+    let (title, content) = if create {
+        (
+            "设置密码",
+            "系统密钥环不可用, 请设置一个用于加密账号信息的密码.",
+        )
+    } else {
+        ("输入密码", "账号信息已加密, 请输入密码.")
+    };
+
+    let dialog = Dialog::new()
+        .title(title)
+        .content(
+            LinearLayout::vertical()
+                .child(TextView::new(content))
+                .child(EditView::new().secret().with_name("edit_passphrase")),
+        )
+        .button("确定", |siv| {
+            let passphrase = siv
+                .call_on_name("edit_passphrase", |view: &mut EditView| {
+                    (*view.get_content()).clone()
+                })
+                .unwrap_or_default();
+
+            if passphrase.is_empty() {
+                siv.add_layer(dialog_error("密码不能为空."));
+                return;
+            }
+
+            // 失败时保留对话框以便重试
+            if load_accounts(siv, Protection::Passphrase(passphrase)) {
+                siv.pop_layer();
+            }
+        });
+
+    if create {
+        dialog.button("不加密保存", |siv| {
+            siv.pop_layer();
+            load_accounts(siv, Protection::Plaintext);
+        })
+    } else {
+        dialog.button("退出", |siv| {
+            siv.quit();
+        })
+    }
+}
+
+fn dialog_login_yggdrasil() -> Dialog {
+    Dialog::new()
+        .title("第三方登录")
+        .content(
+            LinearLayout::vertical()
+                .child(TextView::new("验证服务器: "))
+                .child(EditView::new().with_name("edit_yggdrasil_server"))
+                .child(TextView::new("邮箱或用户名: "))
+                .child(EditView::new().with_name("edit_yggdrasil_username"))
+                .child(TextView::new("密码: "))
+                .child(
+                    EditView::new()
+                        .secret()
+                        .with_name("edit_yggdrasil_password"),
+                ),
+        )
+        .button("确定", |siv| {
+            let mut read = |name: &str| {
+                siv.call_on_name(name, |view: &mut EditView| (*view.get_content()).clone())
+                    .unwrap_or_default()
+            };
+            let server = read("edit_yggdrasil_server");
+            let username = read("edit_yggdrasil_username");
+            let password = read("edit_yggdrasil_password");
+
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            let result = runtime.block_on(async {
+                let api_root = resolve_api_root(&server).await?;
+                YggdrasilAccount::authenticate(&api_root, &username, &password).await
+            });
+
+            match result {
+                Ok(account) => {
+                    siv.pop_layer();
+                    siv.pop_layer();
+                    set_account(siv, Account::Yggdrasil(account));
+                }
+                Err(err) => {
+                    siv.add_layer(dialog_error(&err.to_string()));
+                }
+            }
+        })
+        .button("取消", |siv| {
+            siv.pop_layer();
+        })
+}
+
+/// 离线账号使用旧的 UUID 的选项，默认与原版相同。
+fn legacy_uuid_option() -> LinearLayout {
+    LinearLayout::horizontal()
+        .child(Checkbox::new().with_name("checkbox_legacy_uuid"))
+        .child(TextView::new(" 使用旧版本的 UUID (兼容旧存档)"))
+}
+
+/// 显示选中的账号的玩家名。
+fn show_selected_account(siv: &mut Cursive) {
+    let name = match ACCOUNTS.lock().unwrap().selected() {
+        Some(account) => account.name().to_string(),
+        None => String::new(),
+    };
+
+    siv.call_on_name("player_name", |view: &mut TextView| {
+        view.set_content(name);
+    });
+}
+
+/// 保存并选中账号，然后显示玩家名。
+fn set_account(siv: &mut Cursive, account: Account) {
+    let result = {
+        let mut accounts = ACCOUNTS.lock().unwrap();
+        accounts.add(account);
+        accounts.save()
+    };
+
+    if let Err(err) = result {
+        siv.add_layer(dialog_error(&err));
+    }
+
+    show_selected_account(siv);
+}
+
+fn dialog_accounts() -> Dialog {
+    let mut select = SelectView::<String>::new();
+
+    for account in &ACCOUNTS.lock().unwrap().accounts {
+        let kind = match account {
+            Account::Microsoft(_) => String::from("微软"),
+            Account::Offline { .. } => String::from("离线"),
+            Account::Yggdrasil(account) => account.server.clone(),
+        };
+        select.add_item(
+            format!("{} ({})", account.name(), kind),
+            account.uuid().to_string(),
+        );
+    }
+
+    select.set_on_submit(|siv, uuid: &String| {
+        let result = {
+            let mut accounts = ACCOUNTS.lock().unwrap();
+            accounts.select(uuid);
+            accounts.save()
+        };
+
+        if let Err(err) = result {
+            siv.add_layer(dialog_error(&err));
+        }
+
+        show_selected_account(siv);
+        siv.pop_layer();
+    });
+
+    Dialog::new()
+        .title("账号")
+        .content(select.with_name("select_account").scrollable())
+        .button("删除", |siv| {
+            let uuid = siv
+                .call_on_name("select_account", |view: &mut SelectView<String>| {
+                    let uuid = view.selection()?;
+                    if let Some(index) = view.selected_id() {
+                        let _ = view.remove_item(index);
+                    }
+                    Some((*uuid).clone())
+                })
+                .flatten();
+
+            if let Some(uuid) = uuid {
+                let result = {
+                    let mut accounts = ACCOUNTS.lock().unwrap();
+                    accounts.remove(&uuid);
+                    accounts.save()
+                };
+
+                if let Err(err) = result {
+                    siv.add_layer(dialog_error(&err));
+                }
+
+                show_selected_account(siv);
+            }
+        })
+        .button("关闭", |siv| {
+            siv.pop_layer();
+        })
+}
+
+/// 按启动对话框中的设置生成启动信息，留空的设置使用版本设置中的值。
+///
+/// 返回: 版本设置、游戏文件夹和启动信息，出错时显示错误并返回 `None`。
+fn start_info(siv: &mut Cursive) -> Option<(Instance, PathBuf, LaunchInfo)> {
+    let name = siv
+        .call_on_name("select_version", |view: &mut SelectView<String>| {
+            view.selection()
+        })
+        .flatten();
+
+    let name = match name {
+        Some(result) => (*result).clone(),
+        None => {
+            siv.add_layer(dialog_error("还没有安装任何版本, 请先下载一个版本."));
+            return None;
+        }
+    };
+
+    let java = siv
+        .call_on_name("edit_java_path", |view: &mut EditView| {
+            (*view.get_content()).clone()
+        })
+        .filter(|x| !x.trim().is_empty())
+        .map(PathBuf::from);
+
+    let max_memory = match parse_memory(&edit_content(siv, "edit_max_memory")) {
+        Ok(result) => result,
+        Err(err) => {
+            siv.add_layer(dialog_error(&err));
+            return None;
+        }
+    };
+
+    let jvm_arguments = edit_content(siv, "edit_jvm_arguments");
+
+    // 留空的设置使用版本设置中的值
+    let instance = match Instance::load(&GAME_DIR, &name) {
+        Ok(result) => result,
+        Err(err) => {
+            siv.add_layer(dialog_error(&err));
+            return None;
+        }
+    };
+
+    let java = java.or_else(|| instance.java.clone());
+
+    let mut jvm = instance.jvm_options();
+    if max_memory.is_some() {
+        jvm.max_memory = max_memory;
+    }
+    if !jvm_arguments.trim().is_empty() {
+        jvm.extra_arguments = match command::split_arguments(&jvm_arguments) {
+            Ok(result) => result,
+            Err(err) => {
+                siv.add_layer(dialog_error(&err));
+                return None;
+            }
+        };
+    }
+
+    let demo = siv
+        .call_on_name("checkbox_demo", |checkbox: &mut Checkbox| {
+            checkbox.is_checked()
+        })
+        .unwrap_or_default();
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    // 启动前刷新过期的访问令牌
+    let account = {
+        let mut accounts = ACCOUNTS.lock().unwrap();
+
+        if accounts.selected().map(Account::needs_refresh) == Some(true) {
+            let result = runtime
+                .block_on(accounts.refresh_selected(&AuthEndpoints::default()))
+                .map_err(|err| err.to_string())
+                .and_then(|_| accounts.save());
+
+            if let Err(err) = result {
+                siv.add_layer(dialog_error(&format!("刷新登录失败: {}", err)));
+                return None;
+            }
+        }
+
+        accounts.selected().cloned()
+    };
+
+    let account = match account {
+        Some(result) => result,
+        None => {
+            siv.add_layer(dialog_error("请先登录或设置玩家名."));
+            return None;
+        }
+    };
+
+    let authlib_injector = match &account {
+        Account::Yggdrasil(account) => {
+            match runtime.block_on(AuthlibInjector::prepare(
+                &account.server,
+                &GAME_DIR.libraries(),
+            )) {
+                Ok(result) => Some(result),
+                Err(err) => {
+                    siv.add_layer(dialog_error(&err));
+                    return None;
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let game_dir = match Launcher::new()
+        .with_directory(GAME_DIR.clone())
+        .game_directory(&name)
+    {
+        Ok(result) => result,
+        Err(err) => {
+            siv.add_layer(dialog_error(&err));
+            return None;
+        }
+    };
+
+    let info = LaunchInfo {
+        account,
+        version: String::from(""),
+        name,
+        demo,
+        main_class: None,
+        authlib_injector,
+        java,
+        jvm,
+        window: instance.window,
+        log_file: Some(GAME_DIR.launcher_log()),
+    };
+
+    Some((instance, game_dir, info))
+}
+
+/// 游戏输出对话框中已经读取的日志，按级别和关键字过滤后显示。
+type GameLog = Arc<Mutex<Vec<LogRecord>>>;
+
+/// 日志级别的选项，`TRACE` 即显示全部。
+const LOG_LEVELS: [(&str, LogLevel); 6] = [
+    ("全部", LogLevel::Trace),
+    ("DEBUG", LogLevel::Debug),
+    ("INFO", LogLevel::Info),
+    ("WARN", LogLevel::Warn),
+    ("ERROR", LogLevel::Error),
+    ("FATAL", LogLevel::Fatal),
+];
+
+/// 游戏输出对话框中当前的最低级别和关键字。
+fn log_filter(siv: &mut Cursive) -> (LogLevel, String) {
+    let level = siv
+        .call_on_name("select_log_level", |view: &mut SelectView<LogLevel>| {
+            view.selection()
+        })
+        .flatten()
+        .map(|x| *x)
+        .unwrap_or(LogLevel::Trace);
+
+    (level, edit_content(siv, "edit_log_keyword"))
+}
+
+/// 按当前的过滤条件重新显示所有日志。
+fn refresh_game_log(siv: &mut Cursive, log: &GameLog) {
+    let (level, keyword) = log_filter(siv);
+    let text: String = log
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|x| x.matches(level, &keyword))
+        .map(|x| format!("{}\n", x))
+        .collect();
+
+    siv.call_on_name("text_game_log", |view: &mut TextView| {
+        view.set_content(text);
+    });
+}
+
+/// 记录一条日志，满足过滤条件时显示出来。
+fn append_game_log(siv: &mut Cursive, log: &GameLog, record: LogRecord) {
+    let (level, keyword) = log_filter(siv);
+
+    if record.matches(level, &keyword) {
+        siv.call_on_name("text_game_log", |view: &mut TextView| {
+            view.append(format!("{}\n", record));
+        });
+    }
+
+    log.lock().unwrap().push(record);
+}
+
+fn dialog_game_log(log: GameLog) -> Dialog {
+    let mut select = SelectView::<LogLevel>::new().popup();
+    select.add_all(LOG_LEVELS.iter().map(|(label, level)| (*label, *level)));
+
+    let refresh = log.clone();
+    select.set_on_submit(move |siv, _: &LogLevel| refresh_game_log(siv, &refresh));
+
+    Dialog::new()
+        .title("游戏输出")
+        .content(
+            LinearLayout::vertical()
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("级别: "))
+                        .child(select.with_name("select_log_level"))
+                        .child(TextView::new(" 关键字: "))
+                        .child(
+                            EditView::new()
+                                .on_edit(move |siv, _, _| refresh_game_log(siv, &log))
+                                .with_name("edit_log_keyword")
+                                .fixed_width(30),
+                        ),
+                )
+                .child(
+                    TextView::new("")
+                        .with_name("text_game_log")
+                        .scrollable()
+                        .fixed_size((80, 20)),
+                ),
+        )
+        .button("关闭", |siv| {
+            siv.pop_layer();
+            siv.pop_layer();
+        })
+}
+
+/// 显示游戏输出对话框，在后台读取游戏的输出并显示在 `text_game_log` 中，
+/// 游戏退出后显示退出状态，异常退出时在 `game_dir` 中查找崩溃报告并显示崩溃原因。
+fn follow_game(siv: &mut Cursive, mut game: GameProcess, game_dir: PathBuf) {
+    let log = GameLog::default();
+    siv.add_layer(dialog_game_log(log.clone()));
+
+    let sink = siv.cb_sink().clone();
+
+    thread::spawn(move || {
+        // 标准输出和标准错误分别组合 log4j 事件
+        let mut stdout = Log4jParser::new();
+        let mut stderr = Log4jParser::new();
+        // 最近的输出，用于分析崩溃原因
+        let mut output = VecDeque::new();
+
+        // 只在界面线程中修改日志，避免与重新过滤同时发生
+        let send = |record: LogRecord| {
+            let log = log.clone();
+            sink.send(Box::new(move |siv| append_game_log(siv, &log, record)))
+        };
+
+        for line in game.lines() {
+            if output.len() >= GAME_OUTPUT_TAIL {
+                output.pop_front();
+            }
+            output.push_back(line.line.clone());
+
+            let record = match line.source {
+                LogSource::Stdout => stdout.push(&line.line),
+                LogSource::Stderr => stderr.push(&line.line),
+            };
+
+            let record = match record {
+                Some(result) => result,
+                None => continue,
+            };
+
+            // 界面已经关闭
+            if send(record).is_err() {
+                return;
+            }
+        }
+
+        // 游戏在输出事件的中途退出时，没有结束的事件通常是最重要的错误
+        for record in [stdout.finish(), stderr.finish()].into_iter().flatten() {
+            if send(record).is_err() {
+                return;
+            }
+        }
+
+        let (message, analysis) = match game.wait() {
+            Ok(status) if status.success() => (format!("游戏已退出: {}", status), None),
+            Ok(status) => {
+                let crash_log = find_crash_log(&game_dir, game.started_at());
+                let output = Vec::from(output).join("\n");
+                let analysis = CrashAnalysis::new(status.code(), crash_log, &output);
+                (format!("游戏已退出: {}", status), Some(analysis))
+            }
+            Err(err) => (format!("无法获取游戏的退出状态: {}", err), None),
+        };
+        let _ = send(LogRecord::plain(&message));
+
+        if let Some(analysis) = analysis {
+            // 供脚本读取的分析结果
+            let path = GAME_DIR.crash_analysis();
+            let saved = match analysis.save(&path) {
+                Ok(_) => format!("分析结果已保存到 {}", path.display()),
+                Err(err) => format!("无法保存分析结果: {}", err),
+            };
+            let content = format!("{}\n\n{}", analysis, saved);
+
+            let _ = sink.send(Box::new(move |siv| {
+                siv.add_layer(
+                    Dialog::new()
+                        .title("游戏崩溃了!")
+                        .content(TextView::new(content).scrollable())
+                        .button("确定", |siv| {
+                            siv.pop_layer();
+                        }),
+                );
+            }));
+        }
+    });
+}
+
+fn dialog_login_code() -> Dialog {
+    Dialog::new()
+        .title("Step.2")
+        .content(
+            LinearLayout::vertical()
+                .child(TextView::new(
+                    "现在在重定向的链接中提取出 code 的参数并写在下面的输入框中",
+                ))
+                .child(EditView::new().with_name("edit_login_code")),
+        )
+        .button("确定", |siv| {
+            let code = siv
+                .call_on_name("edit_login_code", |view: &mut EditView| {
+                    (*view.get_content()).clone()
+                })
+                .unwrap_or_default();
+
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+
+            match runtime.block_on(Login::login_from_microsoft(code)) {
+                Ok(account) => {
+                    siv.pop_layer();
+                    siv.pop_layer();
+                    set_account(siv, Account::Microsoft(account));
+                }
+                Err(err) => {
+                    siv.add_layer(dialog_error(&err.to_string()));
+                }
+            }
+        })
+        .button("取消", |siv| {
+            siv.pop_layer();
+        })
+}
+
+/// 用设备代码登录: 显示代码后在后台轮询，完成后更新玩家名。
+fn login_device_code(siv: &mut Cursive) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let endpoints = AuthEndpoints::default();
+
+    let device_code = match runtime.block_on(request_device_code(&endpoints)) {
+        Ok(result) => result,
+        Err(err) => {
+            siv.add_layer(dialog_error(&err.to_string()));
+            return;
+        }
+    };
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_button = cancel.clone();
+
+    siv.add_layer(
+        Dialog::new()
+            .title("设备代码登录")
+            .content(TextView::new(format!(
+                "用你的浏览器打开 {} 并输入代码:\n\n    {}\n\n正在等待登录...",
+                device_code.verification_uri, device_code.user_code
+            )))
+            .button("取消", move |siv| {
+                cancel_button.store(true, Ordering::Relaxed);
+                siv.pop_layer();
+            }),
+    );
+
+    let sink = siv.cb_sink().clone();
+
+    thread::spawn(move || {
+        let result = runtime.block_on(Login::login_from_device_code(
+            &device_code,
+            &endpoints,
+            &cancel,
+        ));
+
+        let _ = sink.send(Box::new(move |siv| match result {
+            Ok(account) => {
+                siv.pop_layer();
+                siv.pop_layer();
+                set_account(siv, Account::Microsoft(account));
+            }
+            // 对话框已经关闭
+            Err(LoginError::Cancelled) => {}
+            Err(err) => {
+                siv.pop_layer();
+                siv.add_layer(dialog_error(&err.to_string()));
+            }
+        }));
+    });
+}
+
+/// 读取一个 `EditView` 的内容。
+fn edit_content(siv: &mut Cursive, name: &str) -> String {
+    siv.call_on_name(name, |view: &mut EditView| (*view.get_content()).clone())
+        .unwrap_or_default()
+}
+
+/// 解析用户输入的内存，单位 MiB，留空时返回 `None`。
+fn parse_memory(text: &str) -> Result<Option<u64>, String> {
+    match text.trim() {
+        "" => Ok(None),
+        text => match text.parse::<u64>() {
+            Ok(result) => Ok(Some(result)),
+            Err(_) => Err(String::from("内存必须是整数.")),
+        },
+    }
+}
+
+/// 把 Unix 时间戳显示为本地时间。
+fn format_time(time: u64) -> String {
+    use chrono::TimeZone;
+
+    match chrono::Local.timestamp_opt(time as i64, 0).single() {
+        Some(result) => result.format("%Y-%m-%d %H:%M").to_string(),
+        None => String::from("未知"),
+    }
+}
+
+/// 重新打开版本列表，用于复制、重命名或删除之后刷新。
+fn reopen_instances(siv: &mut Cursive) {
+    siv.pop_layer();
+    siv.pop_layer();
+    siv.pop_layer();
+    siv.add_layer(dialog_instances());
+}
+
+/// 版本列表中显示的一行，例如 `fabric (1.19, Fabric 0.14.21, Java 17)`。
+fn version_label(version: &InstalledVersion) -> String {
+    let mut details = vec![version.instance.game_version.clone()];
+    if let Some(loader) = &version.loader {
+        details.push(loader.to_string());
+    }
+    if let Some(java_version) = version.java_version {
+        details.push(format!("Java {}", java_version));
+    }
+
+    let status = match &version.status {
+        VersionStatus::Complete => String::new(),
+        VersionStatus::Incomplete { missing } => format!(" [缺少 {} 个文件]", missing.len()),
+        VersionStatus::Broken(_) => String::from(" [已损坏]"),
+    };
+
+    format!(
+        "{} ({}){}",
+        version.instance.name,
+        details.join(", "),
+        status
+    )
+}
+
+/// 所有已经安装的版本，值为版本名称。
+fn select_versions() -> SelectView<String> {
+    let mut select = SelectView::<String>::new();
+    for version in installed_versions(&GAME_DIR) {
+        select.add_item(version_label(&version), version.instance.name);
+    }
+    select
+}
+
+fn dialog_instances() -> Dialog {
+    let mut select = select_versions();
+    select.set_on_submit(|siv, name: &String| match Instance::load(&GAME_DIR, name) {
+        Ok(instance) => siv.add_layer(dialog_instance(instance)),
+        Err(err) => siv.add_layer(dialog_error(&err)),
+    });
+
+    Dialog::new()
+        .title("版本")
+        .content(select.scrollable().min_width(30))
+        .button("关闭", |siv| {
+            siv.pop_layer();
+        })
+}
+
+/// 询问新的版本名称，确定后对 `instance` 执行 `action`，完成后刷新版本列表。
+fn dialog_instance_name(
+    title: &str,
+    instance: Instance,
+    action: fn(&mut Instance, &str) -> Result<(), String>,
+) -> Dialog {
+    Dialog::new()
+        .title(title)
+        .content(
+            LinearLayout::horizontal()
+                .child(TextView::new("新的名称: "))
+                .child(
+                    EditView::new()
+                        .content(instance.name.clone())
+                        .with_name("edit_instance_name")
+                        .fixed_width(20),
+                ),
+        )
+        .button("确定", move |siv| {
+            let name = edit_content(siv, "edit_instance_name");
+            let mut instance = instance.clone();
+
+            match action(&mut instance, &name) {
+                Ok(_) => reopen_instances(siv),
+                Err(err) => siv.add_layer(dialog_error(&err)),
+            }
+        })
+        .button("取消", |siv| {
+            siv.pop_layer();
+        })
+}
+
+fn dialog_instance(instance: Instance) -> Dialog {
+    let loader = match &instance.loader {
+        Some(loader) => loader.to_string(),
+        None => String::from("原版"),
+    };
+    let created = match instance.created {
+        0 => String::from("未知"),
+        time => format_time(time),
+    };
+    let last_played = match instance.last_played {
+        Some(time) => format_time(time),
+        None => String::from("从未启动"),
+    };
+    let (width, height) = match instance.window {
+        Some(window) => (window.width.to_string(), window.height.to_string()),
+        None => (String::new(), String::new()),
+    };
+
+    let save_instance = instance.clone();
+    let clone_instance = instance.clone();
+    let rename_instance = instance.clone();
+    let delete_instance = instance.clone();
+
+    Dialog::new()
+        .title(instance.name.clone())
+        .content(
+            LinearLayout::vertical()
+                .child(TextView::new(format!(
+                    "游戏版本: {}\n加载器: {}\n创建时间: {}\n上次启动: {}",
+                    instance.game_version, loader, created, last_played
+                )))
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("Java 路径: "))
+                        .child(
+                            EditView::new()
+                                .content(
+                                    instance
+                                        .java
+                                        .as_ref()
+                                        .map(|x| x.to_string_lossy().into_owned())
+                                        .unwrap_or_default(),
+                                )
+                                .with_name("edit_instance_java")
+                                .fixed_width(30),
+                        ),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("最大内存 (MiB): "))
+                        .child(
+                            EditView::new()
+                                .content(
+                                    instance
+                                        .max_memory
+                                        .map(|x| x.to_string())
+                                        .unwrap_or_default(),
+                                )
+                                .with_name("edit_instance_memory")
+                                .fixed_width(10),
+                        ),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("JVM 参数: "))
+                        .child(
+                            EditView::new()
+                                .content(shell_words::join(&instance.jvm_arguments))
+                                .with_name("edit_instance_jvm_arguments")
+                                .fixed_width(30),
+                        ),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("窗口大小: "))
+                        .child(
+                            EditView::new()
+                                .content(width)
+                                .with_name("edit_instance_width")
+                                .fixed_width(6),
+                        )
+                        .child(TextView::new(" x "))
+                        .child(
+                            EditView::new()
+                                .content(height)
+                                .with_name("edit_instance_height")
+                                .fixed_width(6),
+                        ),
+                )
+                .child(TextView::new(
+                    "(留空时自动选择 Java, 自动分配内存, 使用默认窗口大小)",
+                ))
+                .child(
+                    LinearLayout::horizontal()
+                        .child(
+                            Checkbox::new()
+                                .with_checked(instance.game_directory == GameDirMode::Isolated)
+                                .with_name("checkbox_instance_isolated"),
+                        )
+                        .child(TextView::new("使用独立的游戏文件夹")),
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new("备注: "))
+                        .child(
+                            EditView::new()
+                                .content(instance.notes.clone())
+                                .with_name("edit_instance_notes")
+                                .fixed_width(30),
+                        ),
+                ),
+        )
+        .button("保存", move |siv| {
+            let mut instance = save_instance.clone();
+
+            let java = edit_content(siv, "edit_instance_java");
+            instance.java = Some(java.trim())
+                .filter(|x| !x.is_empty())
+                .map(PathBuf::from);
+
+            instance.max_memory = match parse_memory(&edit_content(siv, "edit_instance_memory")) {
+                Ok(result) => result,
+                Err(err) => {
+                    siv.add_layer(dialog_error(&err));
+                    return;
+                }
+            };
+
+            instance.jvm_arguments =
+                match command::split_arguments(&edit_content(siv, "edit_instance_jvm_arguments")) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        siv.add_layer(dialog_error(&err));
+                        return;
+                    }
+                };
+
+            let width = edit_content(siv, "edit_instance_width");
+            let height = edit_content(siv, "edit_instance_height");
+            instance.window = match (width.trim(), height.trim()) {
+                ("", "") => None,
+                (width, height) => match (width.parse(), height.parse()) {
+                    (Ok(width), Ok(height)) => Some(WindowSize { width, height }),
+                    _ => {
+                        siv.add_layer(dialog_error("窗口大小必须是两个整数."));
+                        return;
+                    }
+                },
+            };
+
+            let isolated = siv
+                .call_on_name("checkbox_instance_isolated", |checkbox: &mut Checkbox| {
+                    checkbox.is_checked()
+                })
+                .unwrap_or_default();
+
+            // 不勾选时保留自定义的游戏文件夹
+            if isolated {
+                instance.game_directory = GameDirMode::Isolated;
+            } else if instance.game_directory == GameDirMode::Isolated {
+                instance.game_directory = GameDirMode::Shared;
+            }
+
+            instance.notes = edit_content(siv, "edit_instance_notes");
+
+            match instance.save(&GAME_DIR) {
+                Ok(_) => {
+                    siv.pop_layer();
+                }
+                Err(err) => siv.add_layer(dialog_error(&err)),
+            }
+        })
+        .button("复制...", move |siv| {
+            siv.add_layer(dialog_instance_name(
+                "复制版本",
+                clone_instance.clone(),
+                |instance, name| instance.clone_to(&GAME_DIR, name).map(|_| ()),
+            ));
+        })
+        .button("重命名...", move |siv| {
+            siv.add_layer(dialog_instance_name(
+                "重命名版本",
+                rename_instance.clone(),
+                |instance, name| instance.rename(&GAME_DIR, name),
+            ));
+        })
+        .button("删除", move |siv| {
+            let instance = delete_instance.clone();
+
+            siv.add_layer(
+                Dialog::new()
+                    .title("删除版本")
+                    .content(TextView::new(format!(
+                        "确定要删除 {} 吗? 版本文件夹中的所有文件都会被删除.",
+                        instance.name
+                    )))
+                    .button("删除", move |siv| {
+                        match instance.clone().delete(&GAME_DIR) {
+                            Ok(_) => reopen_instances(siv),
+                            Err(err) => siv.add_layer(dialog_error(&err)),
+                        }
+                    })
+                    .button("取消", |siv| {
+                        siv.pop_layer();
+                    }),
+            );
+        })
+        .button("关闭", |siv| {
+            siv.pop_layer();
+        })
+}
+
 fn dialog_main() -> Dialog {
     let change_name_submit = move |siv: &mut Cursive| {
         // 创建新的可变字符串副本
-        let temp = match siv.call_on_name("edit_player_name", |view: &mut EditView| {
-            (*view.get_content()).clone()
-        }) {
-            Some(result) => result,
-            None => String::new(),
-        };
+        let temp = siv
+            .call_on_name("edit_player_name", |view: &mut EditView| {
+                (*view.get_content()).clone()
+            })
+            .unwrap_or_default();
 
         let pattern = Regex::new(r"[^\w]").unwrap();
 
         if pattern.is_match(&temp) {
             siv.add_layer(dialog_error("玩家名只能由英文, 数字, 下划线组成."));
         } else {
-            siv.call_on_name("player_name", |view: &mut TextView| {
-                view.set_content(&temp);
-            });
+            let legacy = siv
+                .call_on_name("checkbox_legacy_uuid", |checkbox: &mut Checkbox| {
+                    checkbox.is_checked()
+                })
+                .unwrap_or_default();
 
-            (*PLAYER_NAME.lock().unwrap()) = temp;
+            let scheme = if legacy {
+                OfflineUuidScheme::Legacy
+            } else {
+                OfflineUuidScheme::Vanilla
+            };
 
             siv.pop_layer();
+            set_account(siv, Account::offline_with(&temp, scheme));
         }
     };
 
@@ -80,7 +1078,11 @@ fn dialog_main() -> Dialog {
         siv.add_layer(
             Dialog::new()
                 .title("更改名字")
-                .content(EditView::default().with_name("edit_player_name"))
+                .content(
+                    LinearLayout::vertical()
+                        .child(EditView::default().with_name("edit_player_name"))
+                        .child(legacy_uuid_option()),
+                )
                 .button("确定", change_name_submit)
                 .button("取消", |siv| {
                     siv.pop_layer();
@@ -94,13 +1096,35 @@ fn dialog_main() -> Dialog {
                 .title("Start")
                 .content(
                     LinearLayout::vertical()
+                        .child(TextView::new("版本:"))
+                        .child(
+                            select_versions()
+                                .with_name("select_version")
+                                .scrollable()
+                                .fixed_height(8),
+                        )
                         .child(
                             LinearLayout::horizontal()
-                                .child(TextView::new("版本名称: "))
+                                .child(TextView::new("Java 路径: "))
+                                .child(EditView::new().with_name("edit_java_path").fixed_width(30)),
+                        )
+                        .child(TextView::new(
+                            "(Java 路径、内存和 JVM 参数留空时使用版本设置)",
+                        ))
+                        .child(
+                            LinearLayout::horizontal()
+                                .child(TextView::new("最大内存 (MiB): "))
+                                .child(
+                                    EditView::new().with_name("edit_max_memory").fixed_width(10),
+                                ),
+                        )
+                        .child(
+                            LinearLayout::horizontal()
+                                .child(TextView::new("JVM 参数: "))
                                 .child(
                                     EditView::new()
-                                        .with_name("edit_version_name")
-                                        .fixed_width(10),
+                                        .with_name("edit_jvm_arguments")
+                                        .fixed_width(30),
                                 ),
                         )
                         .child(
@@ -110,43 +1134,20 @@ fn dialog_main() -> Dialog {
                         ),
                 )
                 .button("启动!", |siv| {
-                    let launcher = Launcher::new();
-
-                    let name = match siv.call_on_name("edit_version_name", |view: &mut EditView| {
-                        (*view.get_content()).clone()
-                    }) {
-                        Some(result) => result,
-                        None => String::new(),
-                    };
+                    let launcher = Launcher::new().with_directory(GAME_DIR.clone());
 
-                    let demo = match siv.call_on_name("checkbox_demo", |checkbox: &mut Checkbox| {
-                        checkbox.is_checked()
-                    }) {
+                    let (mut instance, game_dir, info) = match start_info(siv) {
                         Some(result) => result,
-                        None => false,
-                    };
-
-                    let player_name = PLAYER_NAME.lock().unwrap().to_string();
-
-                    let info = LaunchInfo {
-                        player_name: player_name.clone(),
-                        uuid: generate_uuid_without_hyphens(&player_name),
-                        version: String::from(""),
-                        name,
-                        demo,
+                        None => return,
                     };
 
                     match launcher.start(info) {
-                        Ok(_) => {
-                            siv.add_layer(
-                                Dialog::new()
-                                    .title("完成!")
-                                    .content(TextView::new("操作成功地完成."))
-                                    .button("确定", |siv| {
-                                        siv.pop_layer();
-                                        siv.pop_layer();
-                                    }),
-                            );
+                        Ok(game) => {
+                            follow_game(siv, game, game_dir);
+
+                            if let Err(err) = instance.mark_played(&GAME_DIR) {
+                                siv.add_layer(dialog_error(&err));
+                            }
                         }
                         Err(err) => {
                             siv.add_layer(
@@ -161,114 +1162,151 @@ fn dialog_main() -> Dialog {
                         }
                     }
                 })
+                .button("显示启动命令", |siv| {
+                    let launcher = Launcher::new().with_directory(GAME_DIR.clone());
+
+                    let (_, _, info) = match start_info(siv) {
+                        Some(result) => result,
+                        None => return,
+                    };
+
+                    match launcher.command(&info) {
+                        Ok(command) => siv.add_layer(
+                            Dialog::new()
+                                .title("启动命令")
+                                .content(
+                                    LinearLayout::vertical()
+                                        .child(TextView::new(
+                                            "(其中包含访问令牌, 请不要分享给他人)",
+                                        ))
+                                        .child(
+                                            TextView::new(command.to_string())
+                                                .scrollable()
+                                                .fixed_size((80, 20)),
+                                        ),
+                                )
+                                .button("关闭", |siv| {
+                                    siv.pop_layer();
+                                }),
+                        ),
+                        Err(err) => siv.add_layer(dialog_error(&err)),
+                    }
+                })
                 .button("取消", |siv| {
                     siv.pop_layer();
                 }),
         )
     };
 
-    let login = Dialog::new()
-        .title("步骤 1")
-        .content(TextView::new(&format!("用浏览器打开 {}", LOGIN_SUPER_LINK)))
-        .button("下一步", move |siv| {
-            siv.pop_layer();
-            siv.add_layer(
-                Dialog::new()
-                    .title("步骤 2")
-                    .content(
-                        LinearLayout::vertical()
-                            .child(TextView::new(
-                                "现在在重定向的链接中提取出 code 的参数并写在下面的输入框中",
-                            ))
-                            .child(EditView::new().with_name("edit_login_code")),
-                    )
-                    .button("确定", |siv| {
-                        let code = match siv
-                            .call_on_name("edit_login_code", |view: &mut EditView| {
-                                (*view.get_content()).clone()
-                            }) {
-                            Some(result) => result,
-                            None => String::new(),
-                        };
-
-                        let poster = Post::new();
-
-                        let mut headers = HeaderMap::new();
-                        headers.insert(
-                            "Content-Type",
-                            "application/x-www-form-urlencoded".parse().unwrap(),
-                        );
-
-                        let data = json!({
-                            "client_id": "00000000402b5328",
-                            "code": &code,
-                            "grant_type": "authorization_code",
-                            "redirect_uri": "https://login.live.com/oauth20_desktop.srf",
-                            "scope": "service::user.auth.xboxlive.com::MBI_SSL",
-                        });
-
-                        let runtime = tokio::runtime::Builder::new_multi_thread()
-                            .enable_all()
-                            .build()
-                            .unwrap();
-
-                        match runtime.block_on(poster.post(
-                            "https://login.live.com/oauth20_token.srf",
-                            headers,
-                            data.to_string(),
-                        )) {
-                            Ok(_) => (),
-                            Err(err) => {
-                                siv.add_layer(dialog_error(&err.to_string()));
-                                siv.pop_layer();
-                                return;
-                            }
-                        };
-
-                        let response = poster.get("https://login.live.com/oauth20_token.srf");
-
-                        let response = match runtime.block_on(response) {
-                            Ok(result) => result,
-                            Err(err) => {
-                                siv.add_layer(dialog_error(&err.to_string()));
-                                siv.pop_layer();
-                                return;
-                            }
-                        };
-                    }),
-            );
-        });
-
     let login = move |siv: &mut Cursive| {
         siv.add_layer(
             Dialog::new()
                 .content(TextView::new("注意: 现已不再支持 Mojang 登录!"))
+                .button("用设备代码登录", login_device_code)
                 .button("从微软登录", |siv| {
                     siv.add_layer(
                         Dialog::new()
                             .title("Step.1")
-                            .content(TextView::new(&format!(
+                            .content(TextView::new(format!(
                                 "用你的浏览器打开 {}",
                                 LOGIN_SUPER_LINK
                             )))
                             .button("下一步", |siv| {
                                 siv.pop_layer();
-                                siv.add_layer(Dialog::new());
+                                siv.add_layer(dialog_login_code());
                             }),
                     );
                 })
-                .button("离线游戏", |siv| {
+                .button("第三方登录", |siv| {
+                    siv.add_layer(dialog_login_yggdrasil());
+                })
+                .button("离线游戏", move |siv| {
                     siv.add_layer(
-                        Dialog::new().title("Input").content(
-                            LinearLayout::vertical()
-                                .child(TextView::new("你的名字: "))
-                                .child(EditView::new()),
-                        ),
+                        Dialog::new()
+                            .title("Input")
+                            .content(
+                                LinearLayout::vertical()
+                                    .child(TextView::new("你的名字: "))
+                                    .child(EditView::new().with_name("edit_player_name"))
+                                    .child(legacy_uuid_option()),
+                            )
+                            .button("确定", change_name_submit)
+                            .button("取消", |siv| {
+                                siv.pop_layer();
+                            }),
                     );
                 }),
         )
     };
 
+    let repair_version = move |siv: &mut Cursive| {
+        siv.add_layer(
+            Dialog::new()
+                .title("Repair")
+                .content(
+                    LinearLayout::vertical()
+                        .child(TextView::new("版本:"))
+                        .child(
+                            select_versions()
+                                .with_name("select_repair_version")
+                                .scrollable()
+                                .fixed_height(8),
+                        ),
+                )
+                .button("确定", |siv| {
+                    let name = siv
+                        .call_on_name("select_repair_version", |view: &mut SelectView<String>| {
+                            view.selection()
+                        })
+                        .flatten();
+
+                    let name = match name {
+                        Some(result) => (*result).clone(),
+                        None => {
+                            siv.add_layer(dialog_error("还没有安装任何版本, 请先下载一个版本."));
+                            return;
+                        }
+                    };
+
+                    let download_manager = DownloadManager::new().with_directory(GAME_DIR.clone());
+
+                    match download_manager.repair_version(&name) {
+                        Ok(report) => {
+                            let content = if report.is_ok() {
+                                format!("{}\n无需修复.", report)
+                            } else {
+                                format!("{}\n已重新下载以上文件.", report)
+                            };
+
+                            siv.add_layer(
+                                Dialog::new()
+                                    .title("完成!")
+                                    .content(TextView::new(content).scrollable())
+                                    .button("确定", |siv| {
+                                        siv.pop_layer();
+                                        siv.pop_layer();
+                                    }),
+                            );
+                        }
+                        Err(err) => {
+                            siv.add_layer(
+                                Dialog::new()
+                                    .title("发生错误!")
+                                    .content(TextView::new(&err).scrollable())
+                                    .button("确定", |siv| {
+                                        siv.pop_layer();
+                                        siv.pop_layer();
+                                    }),
+                            );
+                        }
+                    }
+                })
+                .button("取消", |siv| {
+                    siv.pop_layer();
+                }),
+        )
+    };
+
     // return //
     Dialog::new().title("Main").content(
         LinearLayout::horizontal()
@@ -279,7 +1317,11 @@ fn dialog_main() -> Dialog {
                             .child(TextView::new("玩家名 "))
                             .child(TextView::new("").with_name("player_name")),
                     )
-                    .child(Button::new("登录...", login)),
+                    .child(Button::new("更改名字...", change_name))
+                    .child(Button::new("登录...", login))
+                    .child(Button::new("账号...", |siv| {
+                        siv.add_layer(dialog_accounts());
+                    })),
             )
             .child(
                 LinearLayout::vertical()
@@ -337,15 +1379,11 @@ fn dialog_main() -> Dialog {
                                         ),
                                 )
                                 .button("确定", |siv| {
-                                    let version_id = match siv.call_on_name(
-                                        "version_name",
-                                        |view: &mut TextView| {
+                                    let version_id = siv
+                                        .call_on_name("version_name", |view: &mut TextView| {
                                             view.get_content().source().to_owned()
-                                        },
-                                    ) {
-                                        Some(result) => result,
-                                        None => String::new(),
-                                    };
+                                        })
+                                        .unwrap_or_default();
 
                                     let name = match siv
                                         .call_on_name("name", |view: &mut EditView| {
@@ -355,7 +1393,8 @@ fn dialog_main() -> Dialog {
                                         None => String::new(),
                                     };
 
-                                    let download_manager = DownloadManager::new();
+                                    let download_manager =
+                                        DownloadManager::new().with_directory(GAME_DIR.clone());
                                     let result =
                                         download_manager.download_version(&version_id, &name);
 
@@ -389,6 +1428,10 @@ fn dialog_main() -> Dialog {
                                 }),
                         );
                     }))
+                    .child(Button::new("校验并修复一个版本", repair_version))
+                    .child(Button::new("管理版本...", |siv| {
+                        siv.add_layer(dialog_instances());
+                    }))
                     .child(Button::new("退出", |siv| {
                         siv.add_layer(dialog_quit());
                     })),
@@ -397,16 +1440,11 @@ fn dialog_main() -> Dialog {
 }
 
 fn main() {
+    let mut cursive_main = Cursive::default();
+    cursive_main.set_fps(30);
+    cursive_main.add_layer(dialog_main());
 
-    let runtime = tokio::runtime::Builder::new_multi_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap();
-
-    runtime.block_on(Login::login_from_microsoft(String::from("M.C103_BAY.2.0c333741-6c50-ed27-46c1-050fc992fd83")));
+    open_accounts(&mut cursive_main);
 
-    // let mut cursive_main = Cursive::default();
-    // cursive_main.set_fps(30);
-    // cursive_main.add_layer(dialog_main());
-    // cursive_main.run();
+    cursive_main.run();
 }
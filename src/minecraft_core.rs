@@ -1,37 +1,38 @@
-use crate::downloader::downloader;
-use crate::get_path;
-use crate::post::Post;
+use crate::command::{self, JvmOptions, LaunchCommand};
+use crate::directory::GameDirectory;
+use crate::downloader::downloader::{DownloadTask, Scheduler};
+use crate::instance::{Instance, WindowSize};
+use crate::java;
+use crate::model::{
+    self, Artifact, AssetIndexFile, JavaRuntimeFile, JavaRuntimeIndex, JavaRuntimeManifest,
+    VersionJson, VersionManifest,
+};
+use crate::process::GameProcess;
+use crate::rules::{self, Environment};
+use crate::verify::{self, FileStatus};
 use crate::write_to_file;
+use crate::yggdrasil::AuthlibInjector;
 use crossterm::cursor;
 use crossterm::terminal::Clear;
 use crossterm::terminal::ClearType;
 use crossterm::ExecutableCommand;
 use indicatif::ProgressBar;
-use log::Log;
-use reqwest::Error;
-use reqwest::header::HeaderMap;
-use serde_json::Value;
-use serde_json::json;
-use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt;
 use std::fs::create_dir_all;
 use std::io::stdout;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
-use std::process::Stdio;
-use std::sync::{Arc, Mutex};
-use std::thread;
+
+pub use crate::account::Account;
+pub use crate::auth::Login;
+pub use crate::model::GameVersionType;
 
 const VERSION_MANIFEST_URL: &str = "https://bmclapi2.bangbang93.com/mc/game/version_manifest.json";
-const _DOWNLOAD_THREAD_MAX: usize = 256;
 
-#[derive(Debug)]
-pub enum GameVersionType {
-    Release,
-    Snapshot,
-    OldBeta,
-    OldAlpha,
-}
+/// Mojang 提供的 Java 运行时的索引
+pub const JAVA_RUNTIME_INDEX_URL: &str = "https://bmclapi2.bangbang93.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
 
 #[derive(Debug)]
 pub struct GameVersion {
@@ -39,55 +40,60 @@ pub struct GameVersion {
     pub version_type: GameVersionType,
 }
 
-pub struct DownloadManager {}
+#[derive(Default)]
+pub struct DownloadManager {
+    /// 下载调度器，可以调整并发数和重试次数
+    pub scheduler: Scheduler,
+    /// 下载到的游戏文件夹
+    pub directory: GameDirectory,
+}
 
-impl GameVersion {
-    pub fn build() -> Result<Vec<GameVersion>, Error> {
-        let version_manifest = match reqwest::blocking::get(VERSION_MANIFEST_URL) {
-            Ok(result) => match result.text() {
-                Ok(result) => result,
-                Err(err) => return Err(err),
-            },
-            Err(err) => return Err(err),
-        };
+/// 获取并解析版本清单。
+fn fetch_version_manifest() -> Result<VersionManifest, String> {
+    let version_manifest = match reqwest::blocking::get(VERSION_MANIFEST_URL) {
+        Ok(result) => match result.text() {
+            Ok(result) => result,
+            Err(err) => return Err(err.to_string()),
+        },
+        Err(err) => return Err(err.to_string()),
+    };
 
-        let version_manifest_json: Value = serde_json::from_str(&version_manifest).unwrap();
-        // {
-        //     "latest": {
-        //         "release": "1.19",
-        //         "snapshot": "22w24a"
-        //     },
-        //     "versions": [
-        //         {
-        //             "id": "22w24a",
-        //             "type": "snapshot",
-        //             "url": "https://piston-meta.mojang.com/v1/packages/b74d6df246b9b60e39855076ef171aa7071276f7/22w24a.json",
-        //             "time": "2022-06-15T16:26:14+00:00",
-        //             "releaseTime": "2022-06-15T16:21:49+00:00"
-        //         }, ...
-        //     ]
-        // }
-        let mut result = vec![];
-        for item in version_manifest_json["versions"].as_array().unwrap() {
-            result.push(GameVersion {
-                version_id: item["id"].as_str().unwrap().to_string(),
-                version_type: match item["type"].as_str().unwrap() {
-                    "release" => GameVersionType::Release,
-                    "snapshot" => GameVersionType::Snapshot,
-                    "old_beta" => GameVersionType::OldBeta,
-                    "old_alpha" => GameVersionType::OldAlpha,
-                    _ => GameVersionType::Release,
-                },
-            })
-        }
+    Ok(model::from_str(&version_manifest)?)
+}
 
-        Ok(result)
+impl GameVersion {
+    pub fn build() -> Result<Vec<GameVersion>, String> {
+        let version_manifest = fetch_version_manifest()?;
+
+        Ok(version_manifest
+            .versions
+            .into_iter()
+            .map(|item| GameVersion {
+                version_id: item.id,
+                version_type: item.version_type,
+            })
+            .collect())
     }
 }
 
 impl DownloadManager {
     pub fn new() -> DownloadManager {
-        DownloadManager {}
+        DownloadManager {
+            scheduler: Scheduler::new(),
+            directory: GameDirectory::default(),
+        }
+    }
+
+    /// 设置下载到的游戏文件夹。
+    pub fn with_directory(mut self, directory: GameDirectory) -> DownloadManager {
+        self.directory = directory;
+        self
+    }
+
+    /// 设置最大同时下载数。
+    pub fn with_concurrency(mut self, concurrency: usize) -> DownloadManager {
+        self.scheduler.concurrency = concurrency;
+        self
     }
 
     pub fn download_version(&self, version_id: &str, name: &str) -> Result<(), String> {
@@ -97,7 +103,16 @@ impl DownloadManager {
         let _ = stdout.execute(cursor::MoveTo(0, 0));
         let _ = crossterm::terminal::disable_raw_mode();
 
-        let version_manifest = match reqwest::blocking::get(VERSION_MANIFEST_URL) {
+        let version_manifest = fetch_version_manifest()?;
+
+        let res = match version_manifest.find(version_id) {
+            Some(result) => result,
+            None => return Err(String::from("版本未找到")),
+        };
+
+        // ----- version.json ----- //
+
+        let version = match reqwest::blocking::get(&res.url) {
             Ok(result) => match result.text() {
                 Ok(result) => result,
                 Err(err) => return Err(err.to_string()),
@@ -105,653 +120,970 @@ impl DownloadManager {
             Err(err) => return Err(err.to_string()),
         };
 
-        let version_manifest_json: Value = match serde_json::from_str(&version_manifest) {
-            Ok(result) => result,
-            Err(err) => return Err(err.to_string()),
+        let version_json: VersionJson = model::from_str(&version)?;
+
+        create_dir_all(self.directory.natives(name)).unwrap();
+        write_to_file(
+            &self.directory.version_json(name).to_string_lossy(),
+            version.as_bytes(),
+        );
+
+        // 重新下载已有的版本时保留它的设置
+        if !self.directory.instance_settings(name).is_file() {
+            Instance::new(name, version_id).save(&self.directory)?;
+        }
+
+        let mut tasks: Vec<DownloadTask> = version_tasks(
+            &self.directory,
+            name,
+            &version_json,
+            &Environment::current(),
+        )?
+        .into_iter()
+        .map(|(_, task)| task)
+        .collect();
+
+        // ----- assets.json ----- //
+
+        let asset_index = model::require(&version_json.asset_index, "assetIndex")?;
+        let asset_index_task = asset_index_task(&self.directory, &version_json)?;
+
+        // 已有的索引文件校验通过时直接使用
+        let assets = if asset_index_task.check().is_valid() {
+            match std::fs::read_to_string(&asset_index_task.path) {
+                Ok(result) => result,
+                Err(err) => return Err(err.to_string()),
+            }
+        } else {
+            let assets = match reqwest::blocking::get(&asset_index.url) {
+                Ok(result) => match result.text() {
+                    Ok(result) => result,
+                    Err(err) => return Err(err.to_string()),
+                },
+                Err(err) => return Err(err.to_string()),
+            };
+
+            let actual = verify::sha1_bytes(assets.as_bytes());
+            if !actual.eq_ignore_ascii_case(&asset_index.sha1) {
+                return Err(format!(
+                    "{}: {}",
+                    asset_index_task.path,
+                    FileStatus::HashMismatch {
+                        expected: asset_index.sha1.clone(),
+                        actual,
+                    }
+                ));
+            }
+
+            write_to_file(&asset_index_task.path, assets.as_bytes());
+            assets
         };
-        // {
-        //     "latest": {
-        //         "release": "1.19",
-        //         "snapshot": "22w24a"
-        //     },
-        //     "versions": [
-        //         {
-        //             "id": "22w24a",
-        //             "type": "snapshot",
-        //             "url": "https://piston-meta.mojang.com/v1/packages/b74d6df246b9b60e39855076ef171aa7071276f7/22w24a.json",
-        //             "time": "2022-06-15T16:26:14+00:00",
-        //             "releaseTime": "2022-06-15T16:21:49+00:00"
-        //         }, ...
-        //     ]
-        // }
-
-        let res = version_manifest_json["versions"]
-            .as_array()
-            .unwrap()
-            .iter()
-            .find(|item| item["id"] == version_id)
-            .or_else(|| None);
 
-        if res == None {
-            return Err(String::from("版本未找到"));
+        let assets_json: AssetIndexFile = model::from_str(&assets)?;
+        tasks.extend(
+            asset_tasks(&self.directory, &assets_json)
+                .into_iter()
+                .map(|(_, task)| task),
+        );
+
+        // ----- download ----- //
+
+        // 跳过已经存在且校验通过的文件
+        let tasks: Vec<DownloadTask> = tasks
+            .into_iter()
+            .filter(|task| !task.check().is_valid())
+            .collect();
+
+        self.download_all(tasks)?;
+
+        // ----- java ----- //
+
+        // 没有可用的 Java 时下载 Mojang 提供的运行时
+        if let Some(java_version) = &version_json.java_version {
+            let runtimes = java::discover(&self.directory.runtime());
+            if java::select(&runtimes, Some(java_version), &Environment::current()).is_none() {
+                self.download_java_runtime(&java_version.component)?;
+            }
         }
-        let res = res.unwrap();
 
-        let mut urls = vec![];
-        // ----- version.json ----- //
+        Ok(())
+    }
+
+    /// 下载 Mojang 提供的 Java 运行时到游戏文件夹的 `runtime` 中，下载后启动时会自动找到它。
+    ///
+    /// `component`: 版本 json 中的 `javaVersion.component`，例如 `java-runtime-gamma`。
+    ///
+    /// 返回: java 可执行文件的路径。
+    pub fn download_java_runtime(&self, component: &str) -> Result<PathBuf, String> {
+        let platform = match java::runtime_platform(&Environment::current()) {
+            Some(result) => result,
+            None => return Err(String::from("Mojang 没有为当前平台提供 Java 运行时")),
+        };
 
-        let version = match reqwest::blocking::get(res["url"].as_str().unwrap()) {
+        self.download_java_runtime_from(
+            JAVA_RUNTIME_INDEX_URL,
+            component,
+            platform,
+            &self.directory.runtime(),
+        )
+    }
+
+    /// 与 `download_java_runtime` 相同，但使用指定的索引、平台和文件夹。
+    ///
+    /// 运行时安装在 `runtime_dir/<component>/<platform>/<component>` 中，与官方启动器相同。
+    pub fn download_java_runtime_from(
+        &self,
+        index_url: &str,
+        component: &str,
+        platform: &str,
+        runtime_dir: &Path,
+    ) -> Result<PathBuf, String> {
+        let index = match reqwest::blocking::get(index_url) {
             Ok(result) => match result.text() {
                 Ok(result) => result,
                 Err(err) => return Err(err.to_string()),
             },
             Err(err) => return Err(err.to_string()),
         };
+        let index: JavaRuntimeIndex = model::from_str(&index)?;
 
-        create_dir_all(Path::new(&format!(
-            "./.minecraft/versions/{}/natives/",
-            &name
-        )))
-        .unwrap();
-        write_to_file(
-            &format!("./.minecraft/versions/{}/{}.json", &name, &name),
-            version.as_bytes(),
-        );
+        let entry = match index
+            .get(platform)
+            .and_then(|x| x.get(component))
+            .and_then(|x| x.first())
+        {
+            Some(result) => result,
+            None => {
+                return Err(format!(
+                    "找不到 {} 平台的 Java 运行时 {}",
+                    platform, component
+                ))
+            }
+        };
 
-        let version_json: Value = serde_json::from_str(&version).unwrap();
-        // {
-        //     "arguments": {
-        //         ...
-        //     },
-        //     "assetIndex": {
-        //         "id": "1.19",
-        //         "sha1": "d45eb5e0c20e5d753468de3d68b05c45a946f49b",
-        //         "size": 385416,
-        //         "totalSize": 553754183,
-        //         "url": "https://piston-meta.mojang.com/v1/packages/d45eb5e0c20e5d753468de3d68b05c45a946f49b/1.19.json"
-        //     },
-        //     "assets": "1.19",
-        //     "complianceLevel": 1,
-        //     "downloads": {
-        //         "client": {
-        //             "sha1": "dc26b29eb345cbb60e3939af0b7c78fa52a60daa",
-        //             "size": 21550637,
-        //             "url": "https://piston-data.mojang.com/v1/objects/dc26b29eb345cbb60e3939af0b7c78fa52a60daa/client.jar"
-        //         },...
-        //     },
-        //     "id": "22w24a",
-        //     "javaVersion": {
-        //         "component": "java-runtime-gamma",
-        //         "majorVersion": 17
-        //     },
-        //     "libraries": [
-        //         {
-        //             "downloads": {
-        //                 "artifact": {
-        //                     "path": "com/mojang/logging/1.0.0/logging-1.0.0.jar",
-        //                     "sha1": "f6ca3b2eee0b80b384e8ed93d368faecb82dfb9b",
-        //                     "size": 15343,
-        //                     "url": "https://libraries.minecraft.net/com/mojang/logging/1.0.0/logging-1.0.0.jar"
-        //                 }
-        //             },
-        //             "name": "com.mojang:logging:1.0.0"
-        //         },
-        //         ...
-        //     ],
-        //     "logging": {
-        //         "client": {
-        //             "argument": "-Dlog4j.configurationFile=${path}",
-        //             "file": {
-        //                 "id": "client-1.12.xml",
-        //                 "sha1": "bd65e7d2e3c237be76cfbef4c2405033d7f91521",
-        //                 "size": 888,
-        //                 "url": "https://launcher.mojang.com/v1/objects/bd65e7d2e3c237be76cfbef4c2405033d7f91521/client-1.12.xml"
-        //             },
-        //             "type": "log4j2-xml"
-        //         }
-        //     },
-        //     "mainClass": "net.minecraft.client.main.Main",
-        //     "minimumLauncherVersion": 21,
-        //     "releaseTime": "2022-06-15T16:21:49+00:00",
-        //     "time": "2022-06-15T16:21:49+00:00",
-        //     "type": "snapshot"
-        // }
-
-        urls.push((
-            /* path */ format!("./.minecraft/versions/{}/{}.jar", name, name),
-            /* url  */
-            version_json["downloads"]["client"]["url"]
-                .as_str()
-                .unwrap()
-                .to_string(),
-        ));
+        let manifest = match reqwest::blocking::get(&entry.manifest.url) {
+            Ok(result) => match result.text() {
+                Ok(result) => result,
+                Err(err) => return Err(err.to_string()),
+            },
+            Err(err) => return Err(err.to_string()),
+        };
 
-        if !version_json["logging"]["client"]["file"]["id"].is_null() {
-            urls.push((
-                /* path */
-                format!(
-                    "./.minecraft/versions/{}/{}",
-                    name,
-                    version_json["logging"]["client"]["file"]["id"]
-                        .as_str()
-                        .unwrap()
-                ),
-                /* url  */
-                version_json["logging"]["client"]["file"]["url"]
-                    .as_str()
-                    .unwrap()
-                    .to_string(),
+        let actual = verify::sha1_bytes(manifest.as_bytes());
+        if !actual.eq_ignore_ascii_case(&entry.manifest.sha1) {
+            return Err(format!(
+                "{}: {}",
+                entry.manifest.url,
+                FileStatus::HashMismatch {
+                    expected: entry.manifest.sha1.clone(),
+                    actual,
+                }
             ));
         }
 
-        for item in version_json["libraries"].as_array().unwrap() {
-            // 是否有 artifact 键
-            if !item["downloads"]["artifact"].is_null() {
-                urls.push((
-                    /* path */
-                    format!(
-                        "./.minecraft/libraries/{}",
-                        item["downloads"]["artifact"]["path"].as_str().unwrap()
-                    ),
-                    /* url  */
-                    item["downloads"]["artifact"]["url"]
-                        .as_str()
-                        .unwrap()
-                        .to_string(),
-                ));
-            }
+        let manifest: JavaRuntimeManifest = model::from_str(&manifest)?;
+        let platform_dir = runtime_dir.join(component).join(platform);
+        let home = platform_dir.join(component);
 
-            // 是否有 classifiers 键
-            if !item["downloads"]["classifiers"].is_null() {
-                let classifiers = item["downloads"]["classifiers"].borrow();
-
-                // linux
-                if !classifiers["natives-linux"].is_null() {
-                    urls.push((
-                        /* path */
-                        format!(
-                            "./.minecraft/libraries/{}",
-                            classifiers["natives-linux"]["path"].as_str().unwrap()
-                        ),
-                        /* url  */
-                        classifiers["natives-linux"]["url"]
-                            .as_str()
-                            .unwrap()
-                            .to_string(),
-                    ));
-                }
+        self.install_java_runtime(&manifest, &home)?;
+        write_to_file(
+            &platform_dir.join(".version").to_string_lossy(),
+            entry.version.name.as_bytes(),
+        );
+
+        let executable = if cfg!(windows) { "java.exe" } else { "java" };
+        [
+            home.join("bin").join(executable),
+            home.join("jre.bundle/Contents/Home/bin").join(executable),
+        ]
+        .into_iter()
+        .find(|x| x.is_file())
+        .ok_or_else(|| format!("{} 中没有 java", home.display()))
+    }
 
-                // windows
-                if !classifiers["natives-windows"].is_null() {
-                    urls.push((
-                        /* path */
-                        format!(
-                            "./.minecraft/libraries/{}",
-                            classifiers["natives-windows"]["path"].as_str().unwrap()
-                        ),
-                        /* url  */
-                        classifiers["natives-windows"]["url"]
-                            .as_str()
-                            .unwrap()
-                            .to_string(),
-                    ));
+    /// 按文件清单安装 Java 运行时。
+    ///
+    /// 有 LZMA 压缩版本的文件下载压缩版本后解压，解压后按原文件校验。
+    ///
+    /// `home`: 运行时的根文件夹。
+    fn install_java_runtime(
+        &self,
+        manifest: &JavaRuntimeManifest,
+        home: &Path,
+    ) -> Result<(), String> {
+        let mut tasks = vec![];
+        // (压缩文件, 解压后的文件)
+        let mut compressed = vec![];
+        let mut executables = vec![];
+        let mut links = vec![];
+
+        for (path, file) in &manifest.files {
+            let target = home.join(path);
+
+            match file {
+                JavaRuntimeFile::Directory => {
+                    if let Err(err) = create_dir_all(&target) {
+                        return Err(format!("{}: {}", target.display(), err));
+                    }
                 }
+                JavaRuntimeFile::File {
+                    executable,
+                    downloads,
+                } => {
+                    let raw = DownloadTask::new(
+                        target.to_string_lossy().into_owned(),
+                        downloads.raw.url.clone(),
+                    )
+                    .checked(&downloads.raw.sha1, downloads.raw.size);
+
+                    if *executable {
+                        executables.push(target.clone());
+                    }
+
+                    if raw.check().is_valid() {
+                        continue;
+                    }
 
-                // osx
-                if !classifiers["natives-osx"].is_null() {
-                    urls.push((
-                        /* path */
-                        format!(
-                            "./.minecraft/libraries/{}",
-                            classifiers["natives-osx"]["path"].as_str().unwrap()
-                        ),
-                        /* url  */
-                        classifiers["natives-osx"]["url"]
-                            .as_str()
-                            .unwrap()
-                            .to_string(),
-                    ));
+                    match &downloads.lzma {
+                        Some(lzma) => {
+                            let task =
+                                DownloadTask::new(format!("{}.lzma", raw.path), lzma.url.clone())
+                                    .checked(&lzma.sha1, lzma.size);
+                            compressed.push((task.clone(), raw));
+                            tasks.push(task);
+                        }
+                        None => tasks.push(raw),
+                    }
                 }
+                JavaRuntimeFile::Link { target: link } => links.push((target, link.clone())),
             }
         }
 
-        // ----- assets.json ----- //
+        self.download_all(tasks)?;
 
-        let assets =
-            match reqwest::blocking::get(version_json["assetIndex"]["url"].as_str().expect("ERR!"))
-            {
-                Ok(result) => match result.text() {
-                    Ok(result) => result,
-                    Err(err) => return Err(err.to_string()),
-                },
+        for (lzma, raw) in compressed {
+            let data = match std::fs::read(&lzma.path) {
+                Ok(result) => result,
+                Err(err) => return Err(format!("{}: {}", lzma.path, err)),
+            };
+
+            let mut output = vec![];
+            if let Err(err) = lzma_rs::lzma_decompress(&mut data.as_slice(), &mut output) {
+                return Err(format!("解压 {} 时发生错误: {:?}", lzma.path, err));
+            }
+
+            write_to_file(&raw.path, &output);
+            let _ = std::fs::remove_file(&lzma.path);
+
+            let status = raw.check();
+            if !status.is_valid() {
+                return Err(format!("{}: {}", raw.path, status));
+            }
+        }
+
+        for path in executables {
+            set_executable(&path)?;
+        }
+
+        for (path, target) in links {
+            create_link(&path, &target)?;
+        }
+
+        Ok(())
+    }
+
+    /// 校验一个已经安装的版本。
+    ///
+    /// `name`: 版本名称，即 `versions` 下的文件夹名。
+    ///
+    /// 返回: 所有文件的校验结果。资源索引缺失或损坏时，无法校验其中的资源文件。
+    pub fn verify_version(&self, name: &str) -> Result<VerifyReport, String> {
+        let version_json = load_version(&self.directory, name)?;
+
+        let mut tasks = version_tasks(
+            &self.directory,
+            name,
+            &version_json,
+            &Environment::current(),
+        )?;
+        let asset_index_task = asset_index_task(&self.directory, &version_json)?;
+        let asset_index_status = asset_index_task.check();
+
+        // 只有索引文件完好时才能得到资源文件列表
+        if asset_index_status.is_valid() {
+            let assets = match std::fs::read_to_string(&asset_index_task.path) {
+                Ok(result) => result,
                 Err(err) => return Err(err.to_string()),
             };
+            let assets_json: AssetIndexFile = model::from_str(&assets)?;
+            tasks.extend(asset_tasks(&self.directory, &assets_json));
+        }
 
-        write_to_file(
-            &format!(
-                "./.minecraft/assets/indexes/{}.json",
-                version_json["assets"].as_str().unwrap()
-            ),
-            assets.as_bytes(),
-        );
+        let mut report = VerifyReport {
+            checked: 1,
+            problems: vec![],
+        };
 
-        let assets_json: Value = serde_json::from_str(&assets).unwrap();
+        if !asset_index_status.is_valid() {
+            report.problems.push(VerifyProblem {
+                kind: ArtifactKind::AssetIndex,
+                task: asset_index_task,
+                status: asset_index_status,
+            });
+        }
 
-        for (_, obj) in assets_json["objects"].as_object().unwrap() {
-            let hash = obj["hash"].as_str().unwrap();
-            let two = &hash[0..=1];
+        for (kind, task) in tasks {
+            report.checked += 1;
 
-            if !PathBuf::from(format!("./.minecraft/assets/objects/{}/{}", two, hash)).exists() {
-                urls.push((
-                    /* path */ format!("./.minecraft/assets/objects/{}/{}", two, hash),
-                    /* url  */
-                    format!("https://resources.download.minecraft.net/{}/{}", two, hash),
-                ));
+            let status = task.check();
+            if !status.is_valid() {
+                report.problems.push(VerifyProblem { kind, task, status });
             }
         }
 
-        // ----- download ----- //
-        let size = urls.len();
-        let urls = Arc::new(Mutex::new(urls));
-        let progress_bar = Arc::new(Mutex::new(ProgressBar::new(size.try_into().unwrap())));
-        let mut handles = vec![];
+        Ok(report)
+    }
 
-        for _ in 0..size {
-            let urls = urls.clone();
-            let progress_bar = progress_bar.clone();
-            let handle = thread::spawn(move || {
-                let runtime = tokio::runtime::Builder::new_multi_thread()
-                    .enable_all()
-                    .build()
-                    .unwrap();
+    /// 校验一个已经安装的版本，并重新下载缺失或损坏的文件。
+    ///
+    /// `name`: 版本名称，即 `versions` 下的文件夹名。
+    ///
+    /// 返回: 修复前的校验结果。
+    pub fn repair_version(&self, name: &str) -> Result<VerifyReport, String> {
+        let report = self.verify_version(name)?;
 
-                let mut urls = urls.lock().unwrap();
-                let url = urls[0].clone();
-                urls.remove(0);
+        if report.is_ok() {
+            return Ok(report);
+        }
 
-                let progress_bar = progress_bar.lock().unwrap();
+        self.download_all(report.problems.iter().map(|x| x.task.clone()).collect())?;
 
-                runtime.block_on(downloader::download(&url.0, &url.1));
+        // 资源索引修复后，才能校验其中的资源文件
+        let asset_index_repaired = report
+            .problems
+            .iter()
+            .any(|x| x.kind == ArtifactKind::AssetIndex);
 
-                progress_bar.inc(1);
-            });
-            handles.push(handle);
+        if asset_index_repaired {
+            let assets = self.verify_version(name)?;
+            self.download_all(assets.problems.into_iter().map(|x| x.task).collect())?;
         }
 
-        for handle in handles {
-            handle.join().unwrap();
+        Ok(report)
+    }
+
+    /// 用调度器下载所有任务，并汇总失败的文件。
+    fn download_all(&self, tasks: Vec<DownloadTask>) -> Result<(), String> {
+        if tasks.is_empty() {
+            return Ok(());
         }
 
-        progress_bar.clone().lock().unwrap().finish();
+        let progress_bar = ProgressBar::new(tasks.len() as u64);
+        let runtime = match tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(result) => result,
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let failures = runtime.block_on(self.scheduler.run(tasks, &progress_bar));
+        progress_bar.finish();
+
+        if !failures.is_empty() {
+            let report: Vec<String> = failures
+                .iter()
+                .map(|x| format!("{} ({}): {}", x.task.path, x.task.url, x.reason))
+                .collect();
+
+            return Err(format!(
+                "以下 {} 个文件下载或校验失败:\n{}",
+                report.len(),
+                report.join("\n")
+            ));
+        }
 
         Ok(())
     }
 }
 
+/// 文件的种类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Client,
+    Logging,
+    Library,
+    Native,
+    AssetIndex,
+    Asset,
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ArtifactKind::Client => "游戏主文件",
+            ArtifactKind::Logging => "日志配置",
+            ArtifactKind::Library => "库文件",
+            ArtifactKind::Native => "本地库",
+            ArtifactKind::AssetIndex => "资源索引",
+            ArtifactKind::Asset => "资源文件",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 一个校验失败的文件。
+#[derive(Debug, Clone)]
+pub struct VerifyProblem {
+    pub kind: ArtifactKind,
+    pub task: DownloadTask,
+    pub status: FileStatus,
+}
+
+/// `DownloadManager::verify_version` 的结果。
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    /// 校验过的文件数
+    pub checked: usize,
+    /// 缺失或损坏的文件
+    pub problems: Vec<VerifyProblem>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "共校验 {} 个文件, {} 个缺失或损坏",
+            self.checked,
+            self.problems.len()
+        )?;
+
+        for item in &self.problems {
+            write!(f, "\n[{}] {}: {}", item.kind, item.task.path, item.status)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 由版本 json 生成游戏主文件、日志配置、库文件和本地库的下载任务。
+///
+/// `env`: 只下载在这个环境下可用的库文件和对应的本地库，与启动时相同。
+pub fn version_tasks(
+    directory: &GameDirectory,
+    name: &str,
+    version_json: &VersionJson,
+    env: &Environment,
+) -> Result<Vec<(ArtifactKind, DownloadTask)>, String> {
+    let mut tasks = vec![];
+    // 继承其他版本时，游戏主文件和日志配置在被继承的版本文件夹中
+    let jar = version_json.jar.as_deref().unwrap_or(name);
+
+    let downloads = model::require(&version_json.downloads, "downloads")?;
+    tasks.push((
+        ArtifactKind::Client,
+        DownloadTask::new(
+            directory.version_jar(jar).to_string_lossy().into_owned(),
+            downloads.client.url.clone(),
+        )
+        .checked(&downloads.client.sha1, downloads.client.size),
+    ));
+
+    if let Some(client) = version_json
+        .logging
+        .as_ref()
+        .and_then(|x| x.client.as_ref())
+    {
+        tasks.push((
+            ArtifactKind::Logging,
+            DownloadTask::new(
+                directory
+                    .version_dir(jar)
+                    .join(&client.file.id)
+                    .to_string_lossy()
+                    .into_owned(),
+                client.file.url.clone(),
+            )
+            .checked(&client.file.sha1, client.file.size),
+        ));
+    }
+
+    for item in version_json.libraries.iter().filter(|x| x.is_allowed(env)) {
+        // 有 artifact 键，或者只有 Maven 坐标
+        if let Some(file) = item.file() {
+            let mut task = DownloadTask::new(
+                directory.library(&file.path).to_string_lossy().into_owned(),
+                file.url,
+            );
+            task.sha1 = file.sha1;
+            task.size = file.size;
+            tasks.push((ArtifactKind::Library, task));
+        }
+
+        // 当前系统的本地库，例如 `natives-windows-${arch}`
+        if let Some(artifact) = item
+            .native_classifier(env)
+            .and_then(|x| item.classifier(&x))
+        {
+            tasks.push((ArtifactKind::Native, library_task(directory, artifact)));
+        }
+    }
+
+    Ok(tasks)
+}
+
+/// 资源索引文件的下载任务。
+fn asset_index_task(
+    directory: &GameDirectory,
+    version_json: &VersionJson,
+) -> Result<DownloadTask, String> {
+    let asset_index = model::require(&version_json.asset_index, "assetIndex")?;
+
+    Ok(DownloadTask::new(
+        directory
+            .asset_index(model::require(&version_json.assets, "assets")?)
+            .to_string_lossy()
+            .into_owned(),
+        asset_index.url.clone(),
+    )
+    .checked(&asset_index.sha1, asset_index.size))
+}
+
+/// 资源索引中所有资源文件的下载任务。
+fn asset_tasks(
+    directory: &GameDirectory,
+    assets_json: &AssetIndexFile,
+) -> Vec<(ArtifactKind, DownloadTask)> {
+    assets_json
+        .objects
+        .values()
+        .map(|obj| {
+            (
+                ArtifactKind::Asset,
+                DownloadTask::new(
+                    directory
+                        .asset_object(&obj.path())
+                        .to_string_lossy()
+                        .into_owned(),
+                    obj.url(),
+                )
+                .checked(&obj.hash, obj.size),
+            )
+        })
+        .collect()
+}
+
+/// 由库文件的下载项生成下载任务。
+fn library_task(directory: &GameDirectory, artifact: &Artifact) -> DownloadTask {
+    DownloadTask::new(
+        directory
+            .library(&artifact.path)
+            .to_string_lossy()
+            .into_owned(),
+        artifact.url.clone(),
+    )
+    .checked(&artifact.sha1, artifact.size)
+}
+
 pub struct LaunchInfo {
-    pub player_name: String,
-    pub uuid: String,
+    /// 启动游戏使用的账号
+    pub account: Account,
     pub version: String,
     pub name: String,
     pub demo: bool,
+    /// 覆盖版本 json 中的 `mainClass`
+    pub main_class: Option<String>,
+    /// 使用 Yggdrasil 账号时需要加载的 authlib-injector
+    pub authlib_injector: Option<AuthlibInjector>,
+    /// 指定使用的 java，为 `None` 时按版本 json 中的 `javaVersion` 自动选择
+    pub java: Option<PathBuf>,
+    /// 内存和额外的 jvm 参数
+    pub jvm: JvmOptions,
+    /// 游戏窗口的大小，为 `None` 时使用游戏的默认值
+    pub window: Option<WindowSize>,
+    /// 游戏的输出同时写入的文件，为 `None` 时不写入
+    pub log_file: Option<PathBuf>,
 }
 
-pub struct Launcher {}
+/// 读取一个已经安装的版本的 json，并与它继承的版本合并。
+///
+/// `name`: 版本名称，即 `versions` 下的文件夹名。
+pub fn load_version(directory: &GameDirectory, name: &str) -> Result<VersionJson, String> {
+    let mut version = read_version(directory, name)?;
+    let mut inherited = vec![name.to_string()];
+
+    while let Some(parent) = version.inherits_from.clone() {
+        if inherited.contains(&parent) {
+            return Err(format!("版本 {} 存在循环继承", name));
+        }
+
+        version = version.merge(read_version(directory, &parent)?);
+        inherited.push(parent);
+    }
+
+    // 没有指定 jar 时，使用最顶层的版本的 jar
+    if version.jar.is_none() && inherited.len() > 1 {
+        version.jar = inherited.pop();
+    }
+
+    Ok(version)
+}
+
+/// 读取 `versions/<name>/<name>.json`。
+fn read_version(directory: &GameDirectory, name: &str) -> Result<VersionJson, String> {
+    let path = directory.version_json(name);
+    let version = match std::fs::read_to_string(&path) {
+        Ok(result) => result,
+        Err(err) => return Err(format!("{}: {}", path.display(), err)),
+    };
+
+    Ok(model::from_str(&version)?)
+}
+
+#[derive(Default)]
+pub struct Launcher {
+    /// 启动的游戏所在的文件夹
+    pub directory: GameDirectory,
+}
 
 impl Launcher {
     pub fn new() -> Launcher {
-        Launcher {}
+        Launcher {
+            directory: GameDirectory::default(),
+        }
     }
 
-    /// 启动一个游戏。
+    /// 设置启动的游戏所在的文件夹。
+    pub fn with_directory(mut self, directory: GameDirectory) -> Launcher {
+        self.directory = directory;
+        self
+    }
+
+    /// 一个版本的 `${game_directory}`，由版本设置中的游戏文件夹决定。
     ///
-    /// `info`: 要启动的版本的信息
+    /// `name`: 版本名称，即 `versions` 下的文件夹名。
+    pub fn game_directory(&self, name: &str) -> Result<PathBuf, String> {
+        let instance = Instance::load(&self.directory, name)?;
+        Ok(self.directory.game_dir(name, &instance.game_directory))
+    }
+
+    /// 生成启动一个游戏的完整命令，同时解压本地库、准备旧版本的资源文件。
     ///
-    /// 返回: `Ok()` 表示成功，`Err(str)` 表示失败，并返回一个字符串。
+    /// `info`: 要启动的版本的信息
     ///
-    pub fn start(&self, info: LaunchInfo) -> Result<(), String> {
-        let mut class_path = String::new();
+    /// 返回: 启动命令，可以用 `to_string()` 显示为 shell 命令以便调试。
+    pub fn command(&self, info: &LaunchInfo) -> Result<LaunchCommand, String> {
+        let directory = &self.directory;
 
         // manifest.json 不存在
-        if !Path::new(&format!(
-            "./.minecraft/versions/{}/{}.json",
-            info.name, info.name
-        ))
-        .exists()
-        {
-            return Err(format!(
-                "./.minecraft/versions/{}/{}.json 不存在",
-                info.name, info.name
-            ));
+        let version_json = directory.version_json(&info.name);
+        if !version_json.exists() {
+            return Err(format!("{} 不存在", version_json.display()));
         }
 
-        let version_manifest = std::fs::read_to_string(&format!(
-            "./.minecraft/versions/{}/{}.json",
-            info.name, info.name
-        ))
-        .unwrap();
-        let version_manifest: Value = serde_json::from_str(&version_manifest).unwrap();
+        let version_manifest = load_version(directory, &info.name)?;
 
-        // class_path
-        let mut result = version_manifest["libraries"]
-            .as_array()
-            .unwrap()
-            .iter()
-            .filter(|x| {
-                // 如果没有 downloads->artifact 键，返回 false
-                if x["downloads"]["artifact"].is_null() {
-                    return false;
-                }
+        // 模组、配置和存档所在的文件夹，库文件和资源文件仍然共享
+        let game_directory = self.game_directory(&info.name)?;
+        if let Err(err) = create_dir_all(&game_directory) {
+            return Err(format!("{}: {}", game_directory.display(), err));
+        }
 
-                // 如果没有 rules 键，代表所有系统都可用
-                if x["rules"].is_null() {
-                    return true;
-                }
+        // 启动参数中的主类优先，其次是版本 json (或继承的版本) 中的
+        let main_class = match info
+            .main_class
+            .as_ref()
+            .or(version_manifest.main_class.as_ref())
+        {
+            Some(result) => result.clone(),
+            None => return Err(model::ModelError::MissingField(String::from("mainClass")).into()),
+        };
 
-                // (windows, linux, osx)
-                let mut available = (false, false, false);
-
-                for item in x["rules"].as_array().unwrap() {
-                    match item["action"].as_str().unwrap() {
-                        "allow" => {
-                            // 没有 os 键
-                            if item["os"].is_null() {
-                                available = (true, true, true);
-                            }
-                            // 有 os 键
-                            else {
-                                match item["os"]["name"].as_str().unwrap() {
-                                    "windows" => {
-                                        available.0 = true;
-                                    }
-                                    "linux" => {
-                                        available.1 = true;
-                                    }
-                                    "osx" => {
-                                        available.2 = true;
-                                    }
-                                    _ => (),
-                                }
-                            }
-                        }
-                        "disallow" => match item["os"]["name"].as_str().unwrap() {
-                            "windows" => {
-                                available.0 = false;
-                            }
-                            "linux" => {
-                                available.1 = false;
-                            }
-                            "osx" => {
-                                available.2 = false;
-                            }
-                            _ => (),
-                        },
-                        _ => (),
+        let env = Environment::current()
+            .with_feature("is_demo_user", info.demo)
+            .with_feature("has_custom_resolution", info.window.is_some());
+
+        let total_memory = command::system_memory();
+        info.jvm.validate(total_memory)?;
+
+        let java = match &info.java {
+            Some(result) => result.clone(),
+            None => {
+                let runtimes = java::discover(&directory.runtime());
+                match java::select(&runtimes, version_manifest.java_version.as_ref(), &env) {
+                    Some(result) => result.path.clone(),
+                    None => {
+                        let required = version_manifest
+                            .java_version
+                            .as_ref()
+                            .map(|x| x.major_version)
+                            .unwrap_or(java::DEFAULT_JAVA_MAJOR_VERSION);
+
+                        return Err(if required < java::LEGACY_JAVA_MAJOR_VERSION_LIMIT {
+                            format!("找不到 Java {}, 请安装或手动指定", required)
+                        } else {
+                            format!("找不到 Java {} 或更高的版本, 请安装或手动指定", required)
+                        });
                     }
                 }
-
-                if (cfg!(windows) && available.0) || (cfg!(linux) && available.1) || (cfg!(osx) && available.2) {
-                    return true;
-                }
-
-                false
-            });
-
-        // 分析所有得到的 library 项
-        loop {
-            let next = result.next();
-            if next == None {
-                break;
             }
+        };
 
-            let next = next.unwrap();
-            class_path.push_str(
-                get_path(&format!(
-                    "./.minecraft/libraries/{}",
-                    next["downloads"]["artifact"]["path"].as_str().unwrap()
-                ))
-                .to_str()
-                .unwrap(),
-            );
+        // class_path
+        // 如果没有 downloads->artifact 键，跳过
+        let mut class_path: Vec<PathBuf> = version_manifest
+            .libraries
+            .iter()
+            .filter(|x| x.is_allowed(&env))
+            .filter_map(|x| x.file())
+            .map(|x| directory.library(&x.path))
+            .collect();
 
-            if cfg!(windows) {
-                class_path.push(';');
-            } else if cfg!(linux) {
-                class_path.push(':');
-            }
-        }
+        // 游戏主文件，继承其他版本时使用被继承的版本的 jar
+        let jar = version_manifest.jar.as_deref().unwrap_or(&info.name);
+        class_path.push(directory.version_jar(jar));
 
-        // 游戏主文件
-        class_path.push_str(
-            get_path(&format!(
-                "./.minecraft/versions/{}/{}.jar",
-                info.name, info.name
-            ))
-            .to_str()
-            .unwrap(),
-        );
+        let class_path = match std::env::join_paths(class_path) {
+            Ok(result) => result,
+            Err(err) => return Err(err.to_string()),
+        };
 
         // 获取所有 natives 项
-        let mut result = version_manifest["libraries"]
-            .as_array()
-            .unwrap()
+        let result = version_manifest
+            .libraries
             .iter()
-            .filter(|x| {
-                if !x["natives"].is_null() {
-                    return true;
-                }
-                false
-            });
+            .filter(|x| x.natives.is_some() && x.is_allowed(&env));
 
         // 分析所有得到的 natives 项
 
-        let path_str = format!("./.minecraft/versions/{}/natives", info.name);
-        let natives_path = Path::new(&path_str);
+        let natives_path = directory.natives(&info.name);
 
-        loop {
-            let next = result.next();
-            if next == None {
-                break;
+        for next in result {
+            // 如果这个文件夹不存在
+            if let Err(err) = create_dir_all(&natives_path) {
+                return Err(format!("{}: {}", natives_path.display(), err));
             }
 
-            let next = next.unwrap();
+            let classifier = next
+                .native_classifier(&env)
+                .and_then(|x| next.classifier(&x));
 
-            // 如果这个文件夹不存在
-            if !natives_path.exists() {
-                std::fs::create_dir_all(natives_path).unwrap();
-            }
+            if let Some(classifier) = classifier {
+                let path = directory.library(&classifier.path);
+                // 通常排除 `META-INF/`
+                let exclude = match &next.extract {
+                    Some(rule) => rule.exclude.as_slice(),
+                    None => &[],
+                };
 
-            if cfg!(windows) && !next["downloads"]["classifiers"]["natives-windows"].is_null() {
-                crate::extract(
-                    Path::new(&format!(
-                        "./.minecraft/libraries/{}",
-                        next["downloads"]["classifiers"]["natives-windows"]["path"]
-                            .as_str()
-                            .unwrap()
-                    )),
-                    natives_path,
-                )
-                .unwrap();
-            } else if cfg!(linux) && !next["downloads"]["classifiers"]["natives-linux"].is_null() {
-                crate::extract(
-                    Path::new(&format!(
-                        "./.minecraft/libraries/{}",
-                        next["downloads"]["classifiers"]["natives-linux"]["path"]
-                            .as_str()
-                            .unwrap()
-                    )),
-                    natives_path,
-                )
-                .unwrap();
+                if let Err(err) = crate::extract(&path, &natives_path, exclude) {
+                    return Err(format!("{}: {}", path.display(), err));
+                }
             }
         }
 
-        let mut arguments_string = String::new();
+        let asset_index = model::require(&version_manifest.asset_index, "assetIndex")?;
+        let game_assets = prepare_legacy_assets(
+            directory,
+            &game_directory,
+            model::require(&version_manifest.assets, "assets")?,
+        )?;
+
+        let (jvm_arguments, game_arguments) = rules::version_arguments(&version_manifest, &env)?;
+
+        let mut values: HashMap<&str, OsString> = HashMap::from([
+            ("natives_directory", natives_path.into_os_string()),
+            ("launcher_name", "command-minecraft-launcher".into()),
+            ("launcher_version", "0.0.0".into()),
+            ("classpath", class_path),
+            ("auth_player_name", info.account.name().into()),
+            ("version_name", version_manifest.id.as_str().into()),
+            ("game_directory", game_directory.clone().into_os_string()),
+            ("assets_root", directory.assets().into_os_string()),
+            ("game_assets", game_assets.into_os_string()),
+            ("assets_index_name", asset_index.id.as_str().into()),
+            ("auth_uuid", info.account.uuid().into()),
+            ("auth_access_token", info.account.access_token().into()),
+            ("auth_session", info.account.session().into()),
+            ("auth_xuid", info.account.xuid().into()),
+            ("clientid", info.account.client_id().into()),
+            ("user_properties", "{}".into()),
+            ("user_type", info.account.user_type().into()),
+            (
+                "version_type",
+                version_manifest.version_type.as_str().into(),
+            ),
+        ]);
+        if let Some(window) = info.window {
+            values.insert("resolution_width", window.width.to_string().into());
+            values.insert("resolution_height", window.height.to_string().into());
+        }
+
+        let mut args: Vec<OsString> = match &info.authlib_injector {
+            Some(injector) => injector.jvm_arguments(),
+            None => vec![],
+        };
+        args.extend(
+            jvm_arguments
+                .iter()
+                .map(|x| command::substitute(x, &values)),
+        );
 
-        // arguments 这个键还在
-        if !version_manifest["arguments"].is_null() {
-            let mut jvm_arguments = String::new();
+        // 让游戏按 log4j 事件格式输出日志，配置文件由 download_version 下载
+        if let Some(client) = version_manifest
+            .logging
+            .as_ref()
+            .and_then(|x| x.client.as_ref())
+        {
+            let path = directory.version_dir(jar).join(&client.file.id);
 
-            for item in version_manifest["arguments"]["jvm"].as_array().unwrap() {
-                if item.is_string() {
-                    jvm_arguments.push_str(item.as_str().unwrap());
-                    jvm_arguments.push_str(" ");
-                }
+            if path.is_file() {
+                let values = HashMap::from([("path", path.into_os_string())]);
+                args.push(command::substitute(&client.argument, &values));
             }
+        }
 
-            let mut game_arguments = String::new();
+        args.extend(info.jvm.arguments(total_memory));
+        args.push(main_class.into());
+        args.extend(
+            game_arguments
+                .iter()
+                .map(|x| command::substitute(x, &values)),
+        );
 
-            for item in version_manifest["arguments"]["game"].as_array().unwrap() {
-                if item.is_string() {
-                    game_arguments.push_str(item.as_str().unwrap());
-                    game_arguments.push_str(" ");
-                }
-            }
+        // 旧版本的 minecraftArguments 中没有窗口大小的参数
+        if let (Some(window), None) = (info.window, &version_manifest.arguments) {
+            args.extend([
+                "--width".into(),
+                window.width.to_string().into(),
+                "--height".into(),
+                window.height.to_string().into(),
+            ]);
+        }
 
-            arguments_string = format!(
-                "{} net.minecraft.client.main.Main {}",
-                jvm_arguments, game_arguments
-            );
+        Ok(LaunchCommand {
+            program: java.into_os_string(),
+            args,
+            current_dir: Some(game_directory),
+        })
+    }
 
-            arguments_string = arguments_string
-                .replace(
-                    "${natives_directory}",
-                    get_path(&path_str).to_str().unwrap(),
-                )
-                .replace("${launcher_name}", "command-minecraft-launcher")
-                .replace("${launcher_version}", "0.0.0")
-                .replace("${classpath}", &class_path)
-                .replace("${auth_player_name}", &info.player_name)
-                .replace("${version_name}", version_manifest["id"].as_str().unwrap())
-                .replace(
-                    "${game_directory}",
-                    get_path("./.minecraft/").to_str().unwrap(),
-                )
-                .replace(
-                    "${assets_root}",
-                    get_path("./.minecraft/assets/").to_str().unwrap(),
-                )
-                .replace(
-                    "${assets_index_name}",
-                    version_manifest["assetIndex"]["id"].as_str().unwrap(),
-                )
-                .replace("${auth_uuid}", &info.uuid)
-                .replace("${auth_access_token}", &info.uuid)
-                .replace("${user_type}", "msa")
-                .replace(
-                    "${version_type}",
-                    version_manifest["type"].as_str().unwrap(),
-                );
+    /// 启动一个游戏。
+    ///
+    /// `info`: 要启动的版本的信息
+    ///
+    /// 返回: 正在运行的游戏，可以从中读取游戏的输出和退出状态。
+    pub fn start(&self, info: LaunchInfo) -> Result<GameProcess, String> {
+        let command = self.command(&info)?;
+        GameProcess::spawn(&command, info.log_file.as_deref())
+    }
+}
+
+/// 给文件加上可执行权限，Windows 上不需要。
+fn set_executable(path: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Err(err) = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)) {
+            return Err(format!("{}: {}", path.display(), err));
         }
+    }
 
-        match Command::new("java")
-            .stderr(Stdio::null())
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .args(arguments_string.split_whitespace().collect::<Vec<&str>>())
-            .spawn()
-        {
-            Ok(_) => return Ok(()),
-            Err(error) => return Err(error.to_string()),
+    #[cfg(not(unix))]
+    let _ = path;
+
+    Ok(())
+}
+
+/// 创建符号链接，已经存在时替换。Windows 上的运行时没有符号链接，不做处理。
+///
+/// `target`: 相对于链接所在文件夹的路径。
+fn create_link(path: &Path, target: &str) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        if let Some(parent) = path.parent() {
+            let _ = create_dir_all(parent);
+        }
+
+        if path.symlink_metadata().is_ok() {
+            let _ = std::fs::remove_file(path);
         }
 
-	Ok(())
+        if let Err(err) = std::os::unix::fs::symlink(target, path) {
+            return Err(format!("{}: {}", path.display(), err));
+        }
     }
-}
 
-pub struct Login {
-    logged: bool,
-    name: String,
-    uuid: String,
-    refresh_id: String,
+    #[cfg(not(unix))]
+    let _ = (path, target);
+
+    Ok(())
 }
 
-impl Login {
-    pub fn new() -> Login {
-        Login {
-            logged: false,
-            name: String::new(),
-            uuid: String::new(),
-            refresh_id: String::new(),
+/// 准备旧版本使用的资源文件。
+///
+/// 1.7.2 以前的资源索引带有 `virtual` 或 `map_to_resources`，游戏按文件名而不是 hash 读取资源，
+/// 需要把 `assets/objects` 中的文件复制到 `assets/virtual/<id>` 或游戏文件夹的 `resources` 中。
+///
+/// `game_directory`: 版本的 `${game_directory}`。
+///
+/// `assets`: 资源索引的 id。
+///
+/// 返回: 用于替换 `${game_assets}` 的路径。
+fn prepare_legacy_assets(
+    directory: &GameDirectory,
+    game_directory: &Path,
+    assets: &str,
+) -> Result<PathBuf, String> {
+    let index_path = directory.asset_index(assets);
+    let index = match std::fs::read_to_string(&index_path) {
+        Ok(result) => result,
+        Err(err) => return Err(format!("{}: {}", index_path.display(), err)),
+    };
+    let index: AssetIndexFile = model::from_str(&index)?;
+
+    let virtual_path = directory.virtual_assets(assets);
+    let mut targets = vec![];
+
+    if index.is_virtual {
+        targets.push(virtual_path.clone());
+    }
+
+    if index.map_to_resources {
+        targets.push(game_directory.join("resources"));
+    }
+
+    for (name, obj) in &index.objects {
+        for target in &targets {
+            let target = target.join(name);
+            if target.exists() {
+                continue;
+            }
+
+            if let Some(parent) = target.parent() {
+                if let Err(err) = create_dir_all(parent) {
+                    return Err(err.to_string());
+                }
+            }
+
+            let source = directory.asset_object(&obj.path());
+            if let Err(err) = std::fs::copy(&source, &target) {
+                return Err(format!("{}: {}", source.display(), err));
+            }
         }
     }
 
-    pub async fn login_from_microsoft(code: String) -> Result<Login, reqwest::Error> {
-
-        let mut result = Login::new();
-
-        let mut headers = HeaderMap::new();
-        headers.insert("Content-Type", "application/x-www-form-urlencoded".parse().unwrap());
-
-        let data = json!({
-            "client_id": "00000000402b5328",
-            "code": &code,
-            "grant_type": "authorization_code",
-            "redirect_uri": "https://login.live.com/oauth20_desktop.srf",
-            "scope": "service::user.auth.xboxlive.com::MBI_SSL",
-        }).to_string();
-
-        let poster = Post::new();
-        let response = poster.post("https://login.live.com/oauth20_token.srf", headers, data).await?.json::<Value>().await?;
-        
-        println!("{:?}", json!({
-            "client_id": "00000000402b5328",
-            "code": &code,
-            "grant_type": "authorization_code",
-            "redirect_uri": "https://login.live.com/oauth20_desktop.srf",
-            "scope": "service::user.auth.xboxlive.com::MBI_SSL",
-        }).to_string());
-        
-        Ok(result)
-        // result.refresh_id = String::from(response["refresh_token"].as_str().unwrap());
-
-        // let mut headers = HeaderMap::new();
-        // headers.insert("Content-Type", "application/json".parse().unwrap());
-        // headers.insert("Accept", "application/json".parse().unwrap());
-
-        // let data = json!({
-        //     "Properties": {
-        //         "AuthMethod": "RPS",
-        //         "SiteName": "user.auth.xboxlive.com",
-        //         "RpsTicket": &format!("d={}", response["access_token"].as_str().unwrap()),
-        //     },
-        //     "RelyingParty": "http://auth.xboxlive.com",
-        //     "TokenType": "JWT",
-        // }).to_string();
-
-        // let response = poster.post("https://user.auth.xboxlive.com/user/authenticate", headers, data).await?.json::<Value>().await?;
-
-        // let uhs = response["DisplayClaims"]["xui"].as_array().unwrap()[0].as_str().unwrap();
-
-        // let mut headers = HeaderMap::new();
-        // headers.insert("Content-Type", "application/json".parse().unwrap());
-        // headers.insert("Accept", "application/json".parse().unwrap());
-
-        // let data = json!({
-        //     "Properties": {
-        //         "SandboxId": "RETAIL",
-        //         "UserTokens": [
-        //             response["Token"].as_str().unwrap()
-        //         ]
-        //     },
-        //     "RelyingParty": "rp://api.minecraftservices.com/",
-        //     "TokenType": "JWT"
-        // }).to_string();
-
-        // let response = poster.post("https://user.auth.xboxlive.com/user/authenticate", headers, data).await?.json::<Value>().await?;
-
-        // let mut headers = HeaderMap::new();
-        // headers.insert("Content-Type", "application/json".parse().unwrap());
-        // headers.insert("Accept", "application/json".parse().unwrap());
-
-        // let data = json!({
-        //     "identityToken": &format!("XBL3.0 x={};{}", uhs, response["Token"].as_str().unwrap())
-        // }).to_string();
-
-        // let response = poster.post("https://user.auth.xboxlive.com/user/authenticate", headers, data).await?.json::<Value>().await?;
-
-        // panic!("{}", response["access_token"].as_str().unwrap());
-
-        // Ok(result)
+    if index.is_virtual {
+        create_dir_all(&virtual_path).map_err(|err| err.to_string())?;
+        Ok(virtual_path)
+    } else {
+        Ok(directory.assets())
     }
 }
@@ -0,0 +1,530 @@
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 解析版本清单或版本 json 时发生的错误。
+///
+/// 与直接 `unwrap()` 不同，这里会给出出错字段的路径，例如 `libraries[3].downloads.artifact.url`。
+#[derive(Debug)]
+pub enum ModelError {
+    /// json 格式错误或字段类型不匹配。
+    Parse { field: String, message: String },
+    /// 当前用途下必需、但 json 中没有的字段。
+    MissingField(String),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Parse { field, message } => {
+                write!(f, "解析字段 {} 时发生错误: {}", field, message)
+            }
+            ModelError::MissingField(field) => write!(f, "缺少字段 {}", field),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<ModelError> for String {
+    fn from(err: ModelError) -> String {
+        err.to_string()
+    }
+}
+
+/// 将 json 字符串解析为指定的类型。
+///
+/// `json`: 要解析的 json 字符串。
+///
+/// 返回: 解析失败时，`ModelError::Parse` 会带上出错字段的路径。
+pub fn from_str<T: DeserializeOwned>(json: &str) -> Result<T, ModelError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| ModelError::Parse {
+        field: err.path().to_string(),
+        message: err.inner().to_string(),
+    })
+}
+
+/// 取出一个可选字段，不存在时返回 `ModelError::MissingField`。
+pub fn require<'a, T>(value: &'a Option<T>, field: &str) -> Result<&'a T, ModelError> {
+    value
+        .as_ref()
+        .ok_or_else(|| ModelError::MissingField(field.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum GameVersionType {
+    #[default]
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+}
+
+impl From<String> for GameVersionType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "release" => GameVersionType::Release,
+            "snapshot" => GameVersionType::Snapshot,
+            "old_beta" => GameVersionType::OldBeta,
+            "old_alpha" => GameVersionType::OldAlpha,
+            // 未知的类型按正式版处理
+            _ => GameVersionType::Release,
+        }
+    }
+}
+
+impl From<GameVersionType> for String {
+    fn from(value: GameVersionType) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl GameVersionType {
+    /// 版本 json 中使用的名称，用于替换 `${version_type}`。
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameVersionType::Release => "release",
+            GameVersionType::Snapshot => "snapshot",
+            GameVersionType::OldBeta => "old_beta",
+            GameVersionType::OldAlpha => "old_alpha",
+        }
+    }
+}
+
+/// `version_manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionManifest {
+    pub latest: LatestVersion,
+    pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersion {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestVersion {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: GameVersionType,
+    pub url: String,
+    pub time: String,
+    pub release_time: String,
+}
+
+impl VersionManifest {
+    /// 查找指定 id 的版本。
+    pub fn find(&self, version_id: &str) -> Option<&ManifestVersion> {
+        self.versions.iter().find(|item| item.id == version_id)
+    }
+}
+
+/// `versions/<name>/<name>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJson {
+    pub id: String,
+    #[serde(default, rename = "type")]
+    pub version_type: GameVersionType,
+    /// 继承的版本，mod 加载器的版本 json 通常只写出与原版不同的部分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inherits_from: Option<String>,
+    /// 使用哪个版本文件夹中的 jar，没有时使用自己的
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jar: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_class: Option<String>,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    /// 1.13 及以后的版本使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Arguments>,
+    /// 1.12.2 及以前的版本使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minecraft_arguments: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_index: Option<AssetIndex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<VersionDownloads>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java_version: Option<JavaVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Logging>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+impl VersionJson {
+    /// 与继承的版本合并。
+    ///
+    /// 自己的字段优先；库文件排在继承的版本之前；`arguments` 中的参数追加在继承的版本之后。
+    /// 合并后的 `inheritsFrom` 是继承的版本的 `inheritsFrom`。
+    pub fn merge(self, parent: VersionJson) -> VersionJson {
+        let mut libraries = self.libraries;
+        libraries.extend(parent.libraries);
+
+        let arguments = match (parent.arguments, self.arguments) {
+            (Some(mut parent), Some(child)) => {
+                parent.game.extend(child.game);
+                parent.jvm.extend(child.jvm);
+                Some(parent)
+            }
+            (parent, child) => child.or(parent),
+        };
+
+        VersionJson {
+            id: self.id,
+            version_type: self.version_type,
+            inherits_from: parent.inherits_from,
+            jar: self.jar.or(parent.jar),
+            main_class: self.main_class.or(parent.main_class),
+            libraries,
+            arguments,
+            minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+            asset_index: self.asset_index.or(parent.asset_index),
+            assets: self.assets.or(parent.assets),
+            downloads: self.downloads.or(parent.downloads),
+            java_version: self.java_version.or(parent.java_version),
+            logging: self.logging.or(parent.logging),
+            release_time: self.release_time.or(parent.release_time),
+            time: self.time.or(parent.time),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionDownloads {
+    pub client: Download,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<Download>,
+}
+
+/// 带有校验信息的下载项。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Download {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+/// 库文件的下载项，`path` 是相对于 `libraries` 文件夹的路径。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    pub path: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Library {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloads: Option<LibraryDownloads>,
+    /// 系统名 -> classifier 名，例如 `"linux": "natives-linux"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natives: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<Vec<Rule>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<ExtractRule>,
+    /// 没有 `downloads` 时所在的 Maven 仓库，模组加载器的库通常只有 `name` 和 `url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// 没有 `downloads` 时的 sha1，有些模组加载器会提供
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// 没有指定 `url` 的库所在的 Maven 仓库
+pub const DEFAULT_LIBRARY_URL: &str = "https://libraries.minecraft.net/";
+
+/// 库的主文件的位置，来自 `downloads.artifact` 或由 Maven 坐标得出。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryFile {
+    /// 相对于 `libraries` 文件夹的路径
+    pub path: String,
+    pub url: String,
+    /// 预期的 sha1，为 `None` 时不校验
+    pub sha1: Option<String>,
+    /// 预期的大小，为 `None` 时不校验
+    pub size: Option<u64>,
+}
+
+/// 由 Maven 坐标得到相对于仓库根目录的路径。
+///
+/// `name`: `group:artifact:version[:classifier][@extension]`，
+/// 例如 `net.fabricmc:fabric-loader:0.14.21` 对应
+/// `net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar`。
+pub fn maven_path(name: &str) -> Option<String> {
+    let (name, extension) = name.split_once('@').unwrap_or((name, "jar"));
+    let mut parts = name.split(':');
+    let (group, artifact, version) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let file = match parts.next() {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact, version, extension),
+    };
+
+    Some(format!(
+        "{}/{}/{}/{}",
+        group.replace('.', "/"),
+        artifact,
+        version,
+        file
+    ))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryDownloads {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<Artifact>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifiers: Option<HashMap<String, Artifact>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractRule {
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl Library {
+    /// 库的主文件。
+    pub fn artifact(&self) -> Option<&Artifact> {
+        self.downloads.as_ref()?.artifact.as_ref()
+    }
+
+    /// 库的主文件的位置，用于下载和 classpath。
+    ///
+    /// 有 `downloads` 时使用其中的 `artifact` (只有本地库的库没有主文件)，
+    /// 否则由 `name` 和 `url` 得出。
+    pub fn file(&self) -> Option<LibraryFile> {
+        if self.downloads.is_some() {
+            return self.artifact().map(|x| LibraryFile {
+                path: x.path.clone(),
+                url: x.url.clone(),
+                sha1: Some(x.sha1.clone()),
+                size: Some(x.size),
+            });
+        }
+
+        let path = maven_path(&self.name)?;
+        let root = self.url.as_deref().unwrap_or(DEFAULT_LIBRARY_URL);
+
+        Some(LibraryFile {
+            url: format!("{}/{}", root.trim_end_matches('/'), path),
+            path,
+            sha1: self.sha1.clone(),
+            size: self.size,
+        })
+    }
+
+    /// 指定 classifier 的文件，例如 `natives-linux`。
+    pub fn classifier(&self, name: &str) -> Option<&Artifact> {
+        self.downloads.as_ref()?.classifiers.as_ref()?.get(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub action: RuleAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<HashMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    /// 正则表达式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    pub url: String,
+}
+
+/// `assets/indexes/<id>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetIndexFile {
+    pub objects: HashMap<String, AssetObject>,
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    #[serde(default)]
+    pub map_to_resources: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetObject {
+    /// 40 位十六进制的 sha1，同时决定文件的路径
+    #[serde(deserialize_with = "sha1_hash")]
+    pub hash: String,
+    pub size: u64,
+}
+
+/// 解析 sha1，不是 40 位十六进制时报错，避免用它拼接路径时出错。
+fn sha1_hash<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let hash = String::deserialize(deserializer)?;
+
+    if hash.len() != 40 || !hash.chars().all(|x| x.is_ascii_hexdigit()) {
+        return Err(de::Error::custom(format!("无效的 sha1: {:?}", hash)));
+    }
+
+    Ok(hash)
+}
+
+impl AssetObject {
+    /// 相对于 `assets/objects` 文件夹的路径。
+    pub fn path(&self) -> String {
+        format!("{}/{}", &self.hash[0..2], self.hash)
+    }
+
+    /// 资源服务器上的地址。
+    pub fn url(&self) -> String {
+        format!("https://resources.download.minecraft.net/{}", self.path())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Arguments {
+    #[serde(default)]
+    pub game: Vec<Argument>,
+    #[serde(default)]
+    pub jvm: Vec<Argument>,
+}
+
+/// `arguments.game` / `arguments.jvm` 中的一项。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl ArgumentValue {
+    pub fn values(&self) -> Vec<&str> {
+        match self {
+            ArgumentValue::Single(value) => vec![value.as_str()],
+            ArgumentValue::Many(values) => values.iter().map(|x| x.as_str()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    pub component: String,
+    pub major_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Logging {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<LoggingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// 例如 `-Dlog4j.configurationFile=${path}`
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type")]
+    pub config_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+/// Java 运行时索引 (`all.json`): 平台 -> 组件 -> 可用的版本。
+pub type JavaRuntimeIndex = HashMap<String, HashMap<String, Vec<JavaRuntimeEntry>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRuntimeEntry {
+    /// 运行时文件清单的下载项
+    pub manifest: Download,
+    pub version: JavaRuntimeVersion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRuntimeVersion {
+    /// 例如 `17.0.8`
+    pub name: String,
+    pub released: String,
+}
+
+/// Java 运行时的文件清单。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRuntimeManifest {
+    /// 相对路径 -> 文件
+    pub files: HashMap<String, JavaRuntimeFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JavaRuntimeFile {
+    Directory,
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: JavaRuntimeDownloads,
+    },
+    Link {
+        /// 相对于链接所在文件夹的路径
+        target: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JavaRuntimeDownloads {
+    pub raw: Download,
+    /// 压缩后的文件，不一定存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lzma: Option<Download>,
+}
@@ -1,8 +1,6 @@
-use std::collections::HashMap;
-
 use reqwest::{header::HeaderMap, Response};
-use serde_json::Value;
 
+#[derive(Default)]
 pub struct Post {}
 
 impl Post {
@@ -18,15 +16,20 @@ impl Post {
     ) -> Result<Response, reqwest::Error> {
         let client = reqwest::Client::new();
 
-        Ok(client
-            .post(url)
-            .headers(headers)
-            .body(data)
-            .send()
-            .await?)
+        client.post(url).headers(headers).body(data).send().await
     }
 
     pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
-        Ok(reqwest::get(url).await?)
+        reqwest::get(url).await
+    }
+
+    pub async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<Response, reqwest::Error> {
+        let client = reqwest::Client::new();
+
+        client.get(url).headers(headers).send().await
     }
 }
@@ -0,0 +1,181 @@
+use crate::command::LaunchCommand;
+use std::fs::{create_dir_all, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+/// 等待接收的输出最多保留的行数，超过时丢弃新的行，日志文件中仍然完整
+pub const LOG_BUFFER_LINES: usize = 10000;
+
+/// 一行输出来自标准输出还是标准错误。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSource {
+    Stdout,
+    Stderr,
+}
+
+/// 游戏输出的一行，不含换行符。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub source: LogSource,
+    pub line: String,
+}
+
+/// 一个正在运行的游戏。
+///
+/// 游戏的标准输出和标准错误由后台线程逐行读取，通过 `lines` 或 `try_lines` 获取。
+#[derive(Debug)]
+pub struct GameProcess {
+    child: Child,
+    lines: Receiver<LogLine>,
+    readers: Vec<JoinHandle<()>>,
+    started: SystemTime,
+}
+
+/// 逐行读取输出，发送给 `GameProcess` 并写入日志文件。
+///
+/// 不是 UTF-8 的内容 (例如 Windows 上的本地编码) 会被替换为 `U+FFFD`。
+/// 接收不及时的行会被丢弃，`GameProcess` 被丢弃后不再发送，只写入日志文件。
+fn read_lines(
+    source: LogSource,
+    output: impl Read + Send + 'static,
+    sender: SyncSender<LogLine>,
+    log_file: Option<Arc<Mutex<File>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut buffer = vec![];
+        let mut forward = true;
+
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+
+            if let Some(file) = &log_file {
+                if let Ok(mut file) = file.lock() {
+                    let _ = writeln!(file, "{}", line);
+                }
+            }
+
+            // 没有人接收时继续读取，避免游戏因为管道写满而阻塞
+            if forward {
+                match sender.try_send(LogLine { source, line }) {
+                    Ok(()) | Err(TrySendError::Full(_)) => {}
+                    Err(TrySendError::Disconnected(_)) => forward = false,
+                }
+            }
+        }
+    })
+}
+
+impl GameProcess {
+    /// 启动游戏并开始读取它的输出。
+    ///
+    /// `log_file`: 游戏的输出同时写入的文件，会覆盖已有的文件。
+    pub fn spawn(command: &LaunchCommand, log_file: Option<&Path>) -> Result<GameProcess, String> {
+        let log_file = match log_file {
+            Some(path) => {
+                if let Some(parent) = path.parent() {
+                    let _ = create_dir_all(parent);
+                }
+
+                match File::create(path) {
+                    Ok(result) => Some(Arc::new(Mutex::new(result))),
+                    Err(err) => return Err(format!("{}: {}", path.display(), err)),
+                }
+            }
+            None => None,
+        };
+
+        let started = SystemTime::now();
+        let mut child = match command
+            .to_command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(result) => result,
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let (sender, lines) = mpsc::sync_channel(LOG_BUFFER_LINES);
+        let mut readers = vec![];
+
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(read_lines(
+                LogSource::Stdout,
+                stdout,
+                sender.clone(),
+                log_file.clone(),
+            ));
+        }
+
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(read_lines(LogSource::Stderr, stderr, sender, log_file));
+        }
+
+        Ok(GameProcess {
+            child,
+            lines,
+            readers,
+            started,
+        })
+    }
+
+    /// 游戏的进程 id。
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// 游戏启动的时间，用于查找这次启动产生的崩溃日志。
+    pub fn started_at(&self) -> SystemTime {
+        self.started
+    }
+
+    /// 按顺序获取输出的每一行，没有新的输出时等待，游戏退出且输出读取完毕后结束。
+    pub fn lines(&self) -> mpsc::Iter<'_, LogLine> {
+        self.lines.iter()
+    }
+
+    /// 获取已经读取到的输出，不等待。
+    pub fn try_lines(&self) -> mpsc::TryIter<'_, LogLine> {
+        self.lines.try_iter()
+    }
+
+    /// 检查游戏是否已经退出，不等待。
+    ///
+    /// 返回: 游戏还在运行时返回 `None`。
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>, String> {
+        self.child.try_wait().map_err(|err| err.to_string())
+    }
+
+    /// 等待游戏退出，并等待输出全部读取完毕。
+    ///
+    /// 读取完毕的输出仍然可以通过 `lines` 获取。
+    pub fn wait(&mut self) -> Result<ExitStatus, String> {
+        let status = self.child.wait().map_err(|err| err.to_string())?;
+
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+
+        Ok(status)
+    }
+
+    /// 强制结束游戏。
+    pub fn kill(&mut self) -> Result<(), String> {
+        self.child.kill().map_err(|err| err.to_string())
+    }
+}
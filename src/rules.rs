@@ -0,0 +1,236 @@
+use crate::model::{Argument, Library, ModelError, Rule, RuleAction, VersionJson};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// 用于匹配规则的运行环境。
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// `windows`, `linux` 或 `osx`
+    pub os_name: String,
+    /// `x86`, `x86_64` 或 `arm64`
+    pub os_arch: String,
+    /// 系统版本，例如 Windows 上的 `10.0`
+    pub os_version: String,
+    /// 例如 `is_demo_user`, `has_custom_resolution`, `is_quick_play_singleplayer`
+    pub features: HashMap<String, bool>,
+}
+
+impl Environment {
+    /// 当前系统的环境，不开启任何特性。
+    pub fn current() -> Environment {
+        let os_name = match std::env::consts::OS {
+            "windows" => "windows",
+            "macos" => "osx",
+            _ => "linux",
+        };
+
+        let os_arch = match std::env::consts::ARCH {
+            "x86" => "x86",
+            "aarch64" => "arm64",
+            "arm" => "arm32",
+            _ => "x86_64",
+        };
+
+        Environment {
+            os_name: os_name.to_string(),
+            os_arch: os_arch.to_string(),
+            os_version: current_os_version(),
+            features: HashMap::new(),
+        }
+    }
+
+    /// 设置一个特性。
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Environment {
+        self.features.insert(name.to_string(), enabled);
+        self
+    }
+
+    /// 用于替换 natives classifier 中 `${arch}` 的位数。
+    pub fn arch_bits(&self) -> &'static str {
+        match self.os_arch.as_str() {
+            "x86" | "arm32" => "32",
+            _ => "64",
+        }
+    }
+}
+
+/// 获取系统版本，与 Java 的 `os.version` 属性格式相同。
+fn current_os_version() -> String {
+    if cfg!(windows) {
+        // Microsoft Windows [Version 10.0.19045.3086]
+        let output = std::process::Command::new("cmd")
+            .args(["/c", "ver"])
+            .output()
+            .map(|x| String::from_utf8_lossy(&x.stdout).into_owned())
+            .unwrap_or_default();
+
+        let pattern = Regex::new(r"(\d+\.\d+)\.\d+").unwrap();
+        pattern
+            .captures(&output)
+            .map(|x| x[1].to_string())
+            .unwrap_or_default()
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .map(|x| String::from_utf8_lossy(&x.stdout).trim().to_string())
+            .unwrap_or_default()
+    } else {
+        std::fs::read_to_string("/proc/sys/kernel/osrelease")
+            .map(|x| x.trim().to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl Rule {
+    /// 判断这条规则是否适用于指定的环境。
+    ///
+    /// `os` 中的每一项和 `features` 中的每一项都匹配时才适用。
+    pub fn matches(&self, env: &Environment) -> bool {
+        if let Some(os) = &self.os {
+            if let Some(name) = &os.name {
+                if *name != env.os_name {
+                    return false;
+                }
+            }
+
+            if let Some(arch) = &os.arch {
+                if *arch != env.os_arch {
+                    return false;
+                }
+            }
+
+            if let Some(version) = &os.version {
+                // 无法解析的正则表达式视为不匹配
+                match Regex::new(version) {
+                    Ok(pattern) if pattern.is_match(&env.os_version) => {}
+                    _ => return false,
+                }
+            }
+        }
+
+        if let Some(features) = &self.features {
+            for (name, expected) in features {
+                if env.features.get(name).copied().unwrap_or(false) != *expected {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// 判断一组规则是否允许。
+///
+/// 与官方启动器相同: 没有规则时允许；有规则时默认不允许，
+/// 依次检查每条规则，适用的规则会覆盖之前的结果，即最后一条适用的规则决定结果。
+pub fn rules_allow(rules: Option<&[Rule]>, env: &Environment) -> bool {
+    let rules = match rules {
+        Some(rules) => rules,
+        None => return true,
+    };
+
+    let mut action = RuleAction::Disallow;
+
+    for rule in rules {
+        if rule.matches(env) {
+            action = rule.action;
+        }
+    }
+
+    action == RuleAction::Allow
+}
+
+impl Library {
+    /// 判断这个库在指定的环境下是否可用。
+    pub fn is_allowed(&self, env: &Environment) -> bool {
+        rules_allow(self.rules.as_deref(), env)
+    }
+
+    /// 指定环境下的 natives classifier 名称，例如 `natives-windows-64`。
+    pub fn native_classifier(&self, env: &Environment) -> Option<String> {
+        self.natives
+            .as_ref()?
+            .get(&env.os_name)
+            .map(|x| x.replace("${arch}", env.arch_bits()))
+    }
+}
+
+/// 展开 `arguments.game` 或 `arguments.jvm`，返回在指定环境下启用的所有参数。
+///
+/// 参数中的 `${...}` 不会被替换。
+pub fn resolve_arguments(arguments: &[Argument], env: &Environment) -> Vec<String> {
+    let mut result = vec![];
+
+    for item in arguments {
+        match item {
+            Argument::Plain(value) => result.push(value.clone()),
+            Argument::Conditional { rules, value } => {
+                if rules_allow(Some(rules), env) {
+                    result.extend(value.values().into_iter().map(String::from));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// 1.12.2 及以前的版本没有 `arguments.jvm`，使用官方启动器默认的 jvm 参数。
+fn legacy_jvm_arguments(env: &Environment) -> Vec<String> {
+    let mut result = vec![];
+
+    if env.os_name == "windows" {
+        result.push(String::from(
+            "-XX:HeapDumpPath=MojangTricksIntelDriversForPerformance_javaw.exe_minecraft.exe.heapdump",
+        ));
+    }
+
+    result.extend(
+        [
+            "-Djava.library.path=${natives_directory}",
+            "-Dminecraft.launcher.brand=${launcher_name}",
+            "-Dminecraft.launcher.version=${launcher_version}",
+            "-cp",
+            "${classpath}",
+        ]
+        .map(String::from),
+    );
+
+    result
+}
+
+/// 展开一个版本的 jvm 参数和游戏参数。
+///
+/// 1.13 及以后的版本使用 `arguments`，之前的版本使用 `minecraftArguments` 和默认的 jvm 参数。
+///
+/// 返回: `(jvm 参数, 游戏参数)`，参数中的 `${...}` 不会被替换。
+pub fn version_arguments(
+    version: &VersionJson,
+    env: &Environment,
+) -> Result<(Vec<String>, Vec<String>), ModelError> {
+    if let Some(arguments) = &version.arguments {
+        return Ok((
+            resolve_arguments(&arguments.jvm, env),
+            resolve_arguments(&arguments.game, env),
+        ));
+    }
+
+    let minecraft_arguments = match &version.minecraft_arguments {
+        Some(result) => result,
+        None => return Err(ModelError::MissingField(String::from("arguments"))),
+    };
+
+    let mut game_arguments: Vec<String> = minecraft_arguments
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+
+    // 旧版本的 minecraftArguments 中没有 demo 参数
+    if env.features.get("is_demo_user").copied().unwrap_or(false) {
+        game_arguments.push(String::from("--demo"));
+    }
+
+    Ok((legacy_jvm_arguments(env), game_arguments))
+}
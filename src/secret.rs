@@ -0,0 +1,170 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// 系统密钥环中保存密钥的服务名
+pub const KEYRING_SERVICE: &str = "command-minecraft-launcher";
+
+/// 系统密钥环中保存密钥的用户名
+pub const KEYRING_USER: &str = "account-store";
+
+/// 保存账号时保护敏感信息的方式。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Protection {
+    /// 用密码派生的密钥加密
+    Passphrase(String),
+    /// 用保存在系统密钥环中的随机密钥加密
+    Keyring,
+    /// 不加密，必须由用户明确选择
+    Plaintext,
+}
+
+impl Protection {
+    /// 去掉密码后的保护方式。
+    pub fn kind(&self) -> ProtectionKind {
+        match self {
+            Protection::Passphrase(_) => ProtectionKind::Passphrase,
+            Protection::Keyring => ProtectionKind::Keyring,
+            Protection::Plaintext => ProtectionKind::Plaintext,
+        }
+    }
+}
+
+/// 账号文件使用的保护方式，不含密码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectionKind {
+    Passphrase,
+    Keyring,
+    Plaintext,
+    /// 加密功能出现之前的明文账号文件
+    Legacy,
+}
+
+/// 加密后的数据，各项都是 base64。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sealed {
+    /// 派生密钥用的盐，仅用密码加密时存在
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    pub nonce: String,
+    pub data: String,
+}
+
+/// 判断系统密钥环是否可用。
+pub fn keyring_available() -> bool {
+    let entry = match keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER) {
+        Ok(result) => result,
+        Err(_) => return false,
+    };
+
+    matches!(entry.get_password(), Ok(_) | Err(keyring::Error::NoEntry))
+}
+
+/// 从系统密钥环中读取密钥，不存在时生成一个新的并保存。
+///
+/// `create`: 密钥不存在时是否生成新的密钥。解密时不应生成。
+fn keyring_key(create: bool) -> Result<Key<Aes256Gcm>, String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|err| format!("无法访问系统密钥环: {}", err))?;
+
+    match entry.get_password() {
+        Ok(encoded) => {
+            let bytes = STANDARD
+                .decode(encoded)
+                .map_err(|err| format!("系统密钥环中的密钥无效: {}", err))?;
+
+            if bytes.len() != 32 {
+                return Err(String::from("系统密钥环中的密钥无效: 长度错误"));
+            }
+
+            Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+        }
+        Err(keyring::Error::NoEntry) if create => {
+            let key = Aes256Gcm::generate_key(OsRng);
+            entry
+                .set_password(&STANDARD.encode(key))
+                .map_err(|err| format!("无法保存密钥到系统密钥环: {}", err))?;
+            Ok(key)
+        }
+        Err(err) => Err(format!("无法从系统密钥环读取密钥: {}", err)),
+    }
+}
+
+/// 用 Argon2id 从密码派生密钥。
+fn passphrase_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, String> {
+    let mut key = Key::<Aes256Gcm>::default();
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| format!("无法从密码派生密钥: {}", err))?;
+
+    Ok(key)
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    STANDARD
+        .decode(value)
+        .map_err(|err| format!("加密数据的 {} 无效: {}", field, err))
+}
+
+/// 加密数据。
+///
+/// `protection`: 不能是 `Protection::Plaintext`。
+pub fn seal(plaintext: &[u8], protection: &Protection) -> Result<Sealed, String> {
+    let (key, salt) = match protection {
+        Protection::Passphrase(passphrase) => {
+            let mut salt = [0u8; 16];
+            aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut salt);
+            (
+                passphrase_key(passphrase, &salt)?,
+                Some(STANDARD.encode(salt)),
+            )
+        }
+        Protection::Keyring => (keyring_key(true)?, None),
+        Protection::Plaintext => return Err(String::from("明文保存不需要加密")),
+    };
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let data = Aes256Gcm::new(&key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| String::from("加密失败"))?;
+
+    Ok(Sealed {
+        salt,
+        nonce: STANDARD.encode(nonce),
+        data: STANDARD.encode(data),
+    })
+}
+
+/// 解密数据。
+///
+/// `protection`: 必须与加密时使用的方式相同。
+pub fn open(sealed: &Sealed, protection: &Protection) -> Result<Vec<u8>, String> {
+    let key = match protection {
+        Protection::Passphrase(passphrase) => {
+            let salt = match &sealed.salt {
+                Some(salt) => decode("salt", salt)?,
+                None => return Err(String::from("加密数据缺少 salt")),
+            };
+            passphrase_key(passphrase, &salt)?
+        }
+        Protection::Keyring => keyring_key(false)?,
+        Protection::Plaintext => return Err(String::from("明文保存不需要解密")),
+    };
+
+    let nonce = decode("nonce", &sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(String::from("加密数据的 nonce 长度错误"));
+    }
+
+    let data = decode("data", &sealed.data)?;
+
+    // 密钥错误和数据被篡改都会导致认证失败
+    Aes256Gcm::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), data.as_ref())
+        .map_err(|_| String::from("解密失败, 密码错误或数据已损坏"))
+}
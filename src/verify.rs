@@ -0,0 +1,115 @@
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 文件的校验结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    /// 文件存在，且大小和 sha1 均与预期一致。
+    Valid,
+    /// 文件不存在。
+    Missing,
+    /// 文件大小与预期不一致。
+    SizeMismatch { expected: u64, actual: u64 },
+    /// 文件 sha1 与预期不一致。
+    HashMismatch { expected: String, actual: String },
+    /// 文件无法读取。
+    Unreadable(String),
+}
+
+impl FileStatus {
+    pub fn is_valid(&self) -> bool {
+        *self == FileStatus::Valid
+    }
+}
+
+impl fmt::Display for FileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileStatus::Valid => write!(f, "校验通过"),
+            FileStatus::Missing => write!(f, "文件不存在"),
+            FileStatus::SizeMismatch { expected, actual } => {
+                write!(f, "大小不匹配 (应为 {}, 实际为 {})", expected, actual)
+            }
+            FileStatus::HashMismatch { expected, actual } => {
+                write!(f, "sha1 不匹配 (应为 {}, 实际为 {})", expected, actual)
+            }
+            FileStatus::Unreadable(err) => write!(f, "无法读取: {}", err),
+        }
+    }
+}
+
+/// 计算一段数据的 sha1，返回小写十六进制字符串。
+pub fn sha1_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(data))
+}
+
+/// 计算一段数据的 sha256，返回小写十六进制字符串。
+pub fn sha256_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// 计算文件的 sha1，返回小写十六进制字符串。
+pub fn sha1_file(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 校验一个文件。
+///
+/// `path`: 文件路径。
+///
+/// `sha1`: 预期的 sha1，为 `None` 时不校验。
+///
+/// `size`: 预期的大小，为 `None` 时不校验。
+pub fn check_file(path: &Path, sha1: Option<&str>, size: Option<u64>) -> FileStatus {
+    let metadata = match std::fs::metadata(path) {
+        Ok(result) => result,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return FileStatus::Missing,
+        Err(err) => return FileStatus::Unreadable(err.to_string()),
+    };
+
+    if !metadata.is_file() {
+        return FileStatus::Missing;
+    }
+
+    // 先比较大小，不一致时就不必计算 sha1 了
+    if let Some(expected) = size {
+        if metadata.len() != expected {
+            return FileStatus::SizeMismatch {
+                expected,
+                actual: metadata.len(),
+            };
+        }
+    }
+
+    if let Some(expected) = sha1 {
+        let actual = match sha1_file(path) {
+            Ok(result) => result,
+            Err(err) => return FileStatus::Unreadable(err.to_string()),
+        };
+
+        if !actual.eq_ignore_ascii_case(expected) {
+            return FileStatus::HashMismatch {
+                expected: expected.to_string(),
+                actual,
+            };
+        }
+    }
+
+    FileStatus::Valid
+}
@@ -0,0 +1,392 @@
+use crate::auth::LoginError;
+use crate::post::Post;
+use crate::verify::sha256_bytes;
+use crate::write_to_file;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::header::HeaderMap;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use url::Url;
+use uuid::Uuid;
+
+/// authlib-injector 最新版本的信息
+pub const AUTHLIB_INJECTOR_URL: &str =
+    "https://bmclapi2.bangbang93.com/mirrors/authlib-injector/artifact/latest.json";
+
+/// authlib-injector 在 libraries 下的路径
+const AUTHLIB_INJECTOR_PATH: &str = "moe/yushi/authlibinjector/authlib-injector";
+
+/// 一个第三方 Yggdrasil 验证服务器的账号。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YggdrasilAccount {
+    /// 验证服务器的 API 地址，例如 `https://example.com/api/yggdrasil`
+    pub server: String,
+    pub name: String,
+    /// 不带连字符的 UUID
+    pub uuid: String,
+    pub access_token: String,
+    pub client_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct GameProfile {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthResponse {
+    access_token: String,
+    client_token: String,
+    selected_profile: Option<GameProfile>,
+    #[serde(default)]
+    available_profiles: Vec<GameProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct YggdrasilError {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    error_message: String,
+}
+
+fn json_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", "application/json".parse().unwrap());
+    headers
+}
+
+/// 把验证服务器返回的错误转换为 `LoginError`，优先使用 `errorMessage`。
+async fn service_error(step: &'static str, response: Response) -> LoginError {
+    let status = response.status().as_u16();
+    let text = response.text().await.unwrap_or_default();
+
+    let message = match serde_json::from_str::<YggdrasilError>(&text) {
+        Ok(err) if !err.error_message.is_empty() => err.error_message,
+        Ok(err) if !err.error.is_empty() => err.error,
+        _ => text,
+    };
+
+    LoginError::Service {
+        step,
+        status,
+        message,
+    }
+}
+
+/// 向验证服务器发送请求。
+///
+/// 返回: 状态码为 2xx 时的响应。
+async fn request(
+    step: &'static str,
+    url: String,
+    body: serde_json::Value,
+) -> Result<Response, LoginError> {
+    let poster = Post::new();
+    let response = poster.post(&url, json_headers(), body.to_string()).await?;
+
+    if !response.status().is_success() {
+        return Err(service_error(step, response).await);
+    }
+
+    Ok(response)
+}
+
+/// 解析用户输入的验证服务器地址。
+///
+/// 服务器可以通过 `X-Authlib-Injector-API-Location` 响应头指向真正的 API 地址 (ALI)。
+///
+/// `url`: 用户输入的地址，没有协议时使用 https。
+pub async fn resolve_api_root(url: &str) -> Result<String, LoginError> {
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+
+    let poster = Post::new();
+    let response = poster.get(&url).await?;
+
+    let location = response
+        .headers()
+        .get("X-Authlib-Injector-API-Location")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| Url::parse(&url).ok()?.join(x).ok());
+
+    let root = match location {
+        Some(result) => result.to_string(),
+        None => url,
+    };
+
+    Ok(root.trim_end_matches('/').to_string())
+}
+
+/// 获取验证服务器的元数据，用于 `-Dauthlibinjector.yggdrasil.prefetched`。
+pub async fn fetch_metadata(api_root: &str) -> Result<String, LoginError> {
+    let poster = Post::new();
+    let response = poster.get(api_root).await?;
+
+    if !response.status().is_success() {
+        return Err(service_error("获取验证服务器信息", response).await);
+    }
+
+    Ok(response.text().await?)
+}
+
+impl YggdrasilAccount {
+    /// 用账号和密码登录。
+    ///
+    /// 账号没有选择角色时选择第一个角色。
+    ///
+    /// `api_root`: 验证服务器的 API 地址，见 `resolve_api_root`。
+    pub async fn authenticate(
+        api_root: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<YggdrasilAccount, LoginError> {
+        let api_root = api_root.trim_end_matches('/');
+        let client_token = Uuid::new_v4().simple().to_string();
+
+        let response = request(
+            "Yggdrasil 登录",
+            format!("{}/authserver/authenticate", api_root),
+            json!({
+                "agent": { "name": "Minecraft", "version": 1 },
+                "username": username,
+                "password": password,
+                "clientToken": client_token,
+                "requestUser": false,
+            }),
+        )
+        .await?;
+        let auth: AuthResponse = response.json().await?;
+
+        let mut account = YggdrasilAccount {
+            server: api_root.to_string(),
+            name: String::new(),
+            uuid: String::new(),
+            access_token: auth.access_token,
+            client_token: auth.client_token,
+        };
+
+        match (auth.selected_profile, auth.available_profiles.first()) {
+            (Some(profile), _) => {
+                account.name = profile.name;
+                account.uuid = profile.id;
+                Ok(account)
+            }
+            (None, Some(profile)) => account.refresh_with(Some(profile)).await,
+            (None, None) => Err(LoginError::Service {
+                step: "Yggdrasil 登录",
+                status: 200,
+                message: String::from("这个账号还没有角色"),
+            }),
+        }
+    }
+
+    /// 刷新访问令牌。
+    ///
+    /// 返回: 使用新的访问令牌的账号。
+    pub async fn refresh(&self) -> Result<YggdrasilAccount, LoginError> {
+        self.refresh_with(None).await
+    }
+
+    /// `profile`: 要选择的角色，为 `None` 时保持当前的角色。
+    async fn refresh_with(
+        &self,
+        profile: Option<&GameProfile>,
+    ) -> Result<YggdrasilAccount, LoginError> {
+        let mut body = json!({
+            "accessToken": self.access_token,
+            "clientToken": self.client_token,
+            "requestUser": false,
+        });
+
+        if let Some(profile) = profile {
+            body["selectedProfile"] = json!(profile);
+        }
+
+        let response = request(
+            "刷新 Yggdrasil 登录",
+            format!("{}/authserver/refresh", self.server),
+            body,
+        )
+        .await?;
+        let auth: AuthResponse = response.json().await?;
+
+        let (name, uuid) = match (auth.selected_profile, profile) {
+            (Some(profile), _) => (profile.name, profile.id),
+            (None, Some(profile)) => (profile.name.clone(), profile.id.clone()),
+            (None, None) => (self.name.clone(), self.uuid.clone()),
+        };
+
+        Ok(YggdrasilAccount {
+            server: self.server.clone(),
+            name,
+            uuid,
+            access_token: auth.access_token,
+            client_token: auth.client_token,
+        })
+    }
+
+    /// 检查访问令牌是否有效。
+    pub async fn validate(&self) -> Result<bool, LoginError> {
+        let poster = Post::new();
+        let body = json!({
+            "accessToken": self.access_token,
+            "clientToken": self.client_token,
+        });
+        let response = poster
+            .post(
+                &format!("{}/authserver/validate", self.server),
+                json_headers(),
+                body.to_string(),
+            )
+            .await?;
+
+        match response.status().as_u16() {
+            204 | 200 => Ok(true),
+            403 => Ok(false),
+            _ => Err(service_error("验证 Yggdrasil 登录", response).await),
+        }
+    }
+
+    /// 吊销访问令牌，用于退出登录。
+    pub async fn invalidate(&self) -> Result<(), LoginError> {
+        request(
+            "退出 Yggdrasil 登录",
+            format!("{}/authserver/invalidate", self.server),
+            json!({
+                "accessToken": self.access_token,
+                "clientToken": self.client_token,
+            }),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// 启动时加载 authlib-injector 需要的信息。
+#[derive(Debug, Clone)]
+pub struct AuthlibInjector {
+    /// authlib-injector 的 jar 文件
+    pub jar: PathBuf,
+    /// 验证服务器的 API 地址
+    pub api_root: String,
+    /// 验证服务器的元数据
+    pub metadata: String,
+}
+
+impl AuthlibInjector {
+    /// 下载 authlib-injector 并获取验证服务器的元数据。
+    ///
+    /// `api_root`: 验证服务器的 API 地址。
+    ///
+    /// `libraries`: libraries 文件夹，见 `GameDirectory::libraries`。
+    pub async fn prepare(api_root: &str, libraries: &Path) -> Result<AuthlibInjector, String> {
+        let jar = download_authlib_injector(AUTHLIB_INJECTOR_URL, libraries).await?;
+        let metadata = fetch_metadata(api_root)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        Ok(AuthlibInjector {
+            jar,
+            api_root: api_root.to_string(),
+            metadata,
+        })
+    }
+
+    /// 需要加在其他 jvm 参数之前的参数。
+    pub fn jvm_arguments(&self) -> Vec<OsString> {
+        let mut javaagent = OsString::from("-javaagent:");
+        javaagent.push(&self.jar);
+        javaagent.push("=");
+        javaagent.push(&self.api_root);
+
+        vec![
+            javaagent,
+            OsString::from(format!(
+                "-Dauthlibinjector.yggdrasil.prefetched={}",
+                STANDARD.encode(&self.metadata)
+            )),
+        ]
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct InjectorArtifact {
+    version: String,
+    download_url: String,
+    checksums: InjectorChecksums,
+}
+
+#[derive(Debug, Deserialize)]
+struct InjectorChecksums {
+    sha256: String,
+}
+
+/// 下载最新的 authlib-injector，已经存在且校验通过时不重新下载。
+///
+/// `latest_url`: 最新版本信息的地址，见 `AUTHLIB_INJECTOR_URL`。
+///
+/// `libraries`: libraries 文件夹。
+///
+/// 返回: jar 文件的路径。
+pub async fn download_authlib_injector(
+    latest_url: &str,
+    libraries: &Path,
+) -> Result<PathBuf, String> {
+    let poster = Post::new();
+
+    let artifact: InjectorArtifact = poster
+        .get(latest_url)
+        .await
+        .map_err(|err| err.to_string())?
+        .json()
+        .await
+        .map_err(|err| format!("解析 authlib-injector 版本信息时发生错误: {}", err))?;
+
+    // 版本号来自服务器，会被用作文件夹和文件名
+    let version = &artifact.version;
+    if version.is_empty() || version.contains(['/', '\\']) || version.contains("..") {
+        return Err(format!("无效的 authlib-injector 版本: {:?}", version));
+    }
+
+    let path = libraries
+        .join(AUTHLIB_INJECTOR_PATH)
+        .join(&artifact.version)
+        .join(format!("authlib-injector-{}.jar", artifact.version));
+
+    if let Ok(data) = std::fs::read(&path) {
+        if sha256_bytes(&data).eq_ignore_ascii_case(&artifact.checksums.sha256) {
+            return Ok(path);
+        }
+    }
+
+    let data = poster
+        .get(&artifact.download_url)
+        .await
+        .map_err(|err| err.to_string())?
+        .bytes()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let actual = sha256_bytes(&data);
+    if !actual.eq_ignore_ascii_case(&artifact.checksums.sha256) {
+        return Err(format!(
+            "authlib-injector 校验失败: sha256 应为 {}, 实际为 {}",
+            artifact.checksums.sha256, actual
+        ));
+    }
+
+    write_to_file(&path.to_string_lossy(), &data);
+    Ok(path)
+}
@@ -0,0 +1,279 @@
+mod common;
+
+use command_minecraft_launcher::account::{Account, AccountStore};
+use command_minecraft_launcher::auth::AuthEndpoints;
+use command_minecraft_launcher::secret::{Protection, ProtectionKind};
+use command_minecraft_launcher::yggdrasil::YggdrasilAccount;
+use command_minecraft_launcher::{generate_uuid_without_hyphens, OfflineUuidScheme};
+use common::MockServer;
+use serde_json::json;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cml-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn offline_accounts_use_vanilla_uuid_by_default() {
+    assert_eq!(
+        Account::offline("Notch").uuid(),
+        "b50ad385829d3141a2167e7d7539ba7f"
+    );
+    assert_eq!(
+        Account::offline_with("Notch", OfflineUuidScheme::Legacy).uuid(),
+        generate_uuid_without_hyphens("Notch")
+    );
+}
+
+#[test]
+fn launch_values_depend_on_account_kind() {
+    // {"xuid":"2535405290000000"}
+    let token = "e30.eyJ4dWlkIjoiMjUzNTQwNTI5MDAwMDAwMCJ9.c2lnbmF0dXJl";
+    let microsoft: Account = serde_json::from_value(json!({
+        "type": "microsoft",
+        "logged": true,
+        "name": "Notch",
+        "uuid": "069a79f444e94726a5befca90e38aaf5",
+        "access_token": token,
+        "refresh_token": "refresh-token",
+    }))
+    .unwrap();
+
+    assert_eq!(microsoft.access_token(), token);
+    assert_eq!(microsoft.user_type(), "msa");
+    assert_eq!(microsoft.xuid(), "2535405290000000");
+
+    let offline = Account::offline("Steve");
+    assert_eq!(offline.access_token(), "0");
+    assert_eq!(offline.user_type(), "legacy");
+    assert_eq!(offline.xuid(), "0");
+
+    let yggdrasil = Account::Yggdrasil(YggdrasilAccount {
+        server: String::from("https://example.com/api/yggdrasil"),
+        name: String::from("Alex"),
+        uuid: String::from("ec561538f3fd461daff5086b22154bce"),
+        access_token: String::from("yggdrasil-token"),
+        client_token: String::from("client-token"),
+    });
+    assert_eq!(yggdrasil.user_type(), "mojang");
+    assert_eq!(yggdrasil.client_id(), "client-token");
+    assert_eq!(
+        yggdrasil.session(),
+        "token:yggdrasil-token:ec561538f3fd461daff5086b22154bce"
+    );
+}
+
+#[test]
+fn accounts_are_saved_and_loaded() {
+    let path = temp_path("accounts.json");
+    let path = path.to_str().unwrap();
+
+    let mut store = AccountStore::load(path, Protection::Plaintext).unwrap();
+    assert!(store.accounts.is_empty());
+
+    store.add(Account::offline("Steve"));
+    store.add(Account::offline("Alex"));
+    store.add(Account::offline("Steve"));
+    assert_eq!(store.accounts.len(), 2);
+    assert_eq!(store.selected().unwrap().name(), "Steve");
+
+    let alex = store.accounts[1].uuid().to_string();
+    assert!(store.select(&alex));
+    assert!(!store.select("unknown"));
+    store.save().unwrap();
+
+    let mut store = AccountStore::load(path, Protection::Plaintext).unwrap();
+    assert_eq!(store.accounts.len(), 2);
+    assert_eq!(store.selected().unwrap().name(), "Alex");
+
+    assert!(store.remove(&alex).is_some());
+    assert!(store.selected().is_none());
+    assert_eq!(store.accounts.len(), 1);
+}
+
+#[test]
+fn failed_save_keeps_the_old_file() {
+    let path = temp_path("atomic.json");
+    let path = path.to_str().unwrap();
+
+    let mut store = AccountStore::load(path, Protection::Plaintext).unwrap();
+    store.add(Account::offline("Steve"));
+    store.save().unwrap();
+    let saved = std::fs::read_to_string(path).unwrap();
+    // 保存后不留下临时文件
+    assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+
+    // 临时文件无法写入时返回错误，原来的文件不变
+    std::fs::create_dir_all(format!("{}.tmp", path)).unwrap();
+    store.add(Account::offline("Alex"));
+    assert!(store.save().is_err());
+    assert_eq!(std::fs::read_to_string(path).unwrap(), saved);
+    std::fs::remove_dir(format!("{}.tmp", path)).unwrap();
+
+    // 文件夹无法创建时同样返回错误，而不是崩溃
+    let blocked = temp_path("blocked");
+    std::fs::write(&blocked, b"").unwrap();
+    let store = AccountStore::load(
+        blocked.join("accounts.json").to_str().unwrap(),
+        Protection::Plaintext,
+    )
+    .unwrap();
+    assert!(store.save().is_err());
+}
+
+fn legacy_file() -> String {
+    json!({
+        "selected": "069a79f444e94726a5befca90e38aaf5",
+        "accounts": [{
+            "type": "microsoft",
+            "logged": true,
+            "name": "Notch",
+            "uuid": "069a79f444e94726a5befca90e38aaf5",
+            "access_token": "old-token",
+            "refresh_token": "old-refresh-token",
+            "expires_at": 0,
+        }],
+    })
+    .to_string()
+}
+
+#[test]
+fn passphrase_protected_store_round_trips() {
+    let path = temp_path("passphrase.json");
+    let path = path.to_str().unwrap();
+    let passphrase = Protection::Passphrase(String::from("correct horse"));
+    std::fs::write(path, legacy_file()).unwrap();
+
+    assert_eq!(
+        AccountStore::stored_protection(path).unwrap(),
+        Some(ProtectionKind::Legacy)
+    );
+
+    // 读取旧的明文文件时立即加密保存
+    let store = AccountStore::load(path, passphrase.clone()).unwrap();
+    assert_eq!(store.selected().unwrap().name(), "Notch");
+    assert_eq!(
+        AccountStore::stored_protection(path).unwrap(),
+        Some(ProtectionKind::Passphrase)
+    );
+
+    let text = std::fs::read_to_string(path).unwrap();
+    assert!(!text.contains("old-token"));
+    assert!(!text.contains("old-refresh-token"));
+
+    let store = AccountStore::load(path, passphrase).unwrap();
+    assert_eq!(store.accounts.len(), 1);
+
+    let wrong = Protection::Passphrase(String::from("wrong"));
+    assert!(AccountStore::load(path, wrong).is_err());
+    assert!(AccountStore::load(path, Protection::Plaintext).is_err());
+}
+
+#[test]
+fn plaintext_must_be_chosen_explicitly() {
+    let path = temp_path("plaintext.json");
+    let path = path.to_str().unwrap();
+    std::fs::write(path, legacy_file()).unwrap();
+
+    let store = AccountStore::load(path, Protection::Plaintext).unwrap();
+    assert_eq!(
+        AccountStore::stored_protection(path).unwrap(),
+        Some(ProtectionKind::Legacy)
+    );
+
+    store.save().unwrap();
+    assert_eq!(
+        AccountStore::stored_protection(path).unwrap(),
+        Some(ProtectionKind::Plaintext)
+    );
+    assert_eq!(
+        AccountStore::load(path, Protection::Plaintext)
+            .unwrap()
+            .accounts
+            .len(),
+        1
+    );
+}
+
+/// 模拟微软登录的服务器，`oauth` 是刷新令牌时的响应。
+fn microsoft_server(oauth: serde_json::Value) -> MockServer {
+    let xbox = json!({
+        "Token": "xbox-token",
+        "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] },
+    })
+    .to_string();
+
+    MockServer::start(vec![
+        ("/oauth20_token.srf", 200, oauth.to_string()),
+        ("/user/authenticate", 200, xbox.clone()),
+        ("/xsts/authorize", 200, xbox),
+        (
+            "/authentication/login_with_xbox",
+            200,
+            json!({ "access_token": "new-token", "expires_in": 86400 }).to_string(),
+        ),
+        (
+            "/entitlements/mcstore",
+            200,
+            json!({ "items": [{ "name": "game_minecraft" }] }).to_string(),
+        ),
+        (
+            "/minecraft/profile",
+            200,
+            json!({ "id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch" }).to_string(),
+        ),
+    ])
+}
+
+#[tokio::test]
+async fn expired_microsoft_account_is_refreshed() {
+    let path = temp_path("expired.json");
+    std::fs::write(&path, legacy_file()).unwrap();
+
+    let server = microsoft_server(
+        json!({ "access_token": "microsoft-token", "refresh_token": "new-refresh-token" }),
+    );
+    let endpoints = AuthEndpoints::with_root(&server.root);
+    let mut store = AccountStore::load(path.to_str().unwrap(), Protection::Plaintext).unwrap();
+    assert!(store.selected().unwrap().needs_refresh());
+    assert!(store.refresh_selected(&endpoints).await.unwrap());
+
+    match store.selected().unwrap() {
+        Account::Microsoft(login) => {
+            assert_eq!(login.access_token(), "new-token");
+            assert_eq!(login.refresh_token(), "new-refresh-token");
+            assert!(!login.is_expired());
+        }
+        account => panic!("unexpected account {:?}", account),
+    }
+
+    // 没有过期时不会再次刷新
+    assert!(!store.refresh_selected(&endpoints).await.unwrap());
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[0].body.contains("grant_type=refresh_token"));
+    assert!(requests[0].body.contains("refresh_token=old-refresh-token"));
+}
+
+#[tokio::test]
+async fn refresh_token_is_kept_when_not_returned() {
+    let path = temp_path("expired-keep.json");
+    std::fs::write(&path, legacy_file()).unwrap();
+
+    let server = microsoft_server(json!({ "access_token": "microsoft-token" }));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+
+    let mut store = AccountStore::load(path.to_str().unwrap(), Protection::Plaintext).unwrap();
+    assert!(store.refresh_selected(&endpoints).await.unwrap());
+
+    match store.selected().unwrap() {
+        Account::Microsoft(login) => {
+            assert_eq!(login.access_token(), "new-token");
+            assert_eq!(login.refresh_token(), "old-refresh-token");
+        }
+        account => panic!("unexpected account {:?}", account),
+    }
+    assert_eq!(server.requests.lock().unwrap().len(), 6);
+}
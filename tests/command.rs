@@ -0,0 +1,122 @@
+use command_minecraft_launcher::command::{
+    auto_memory, split_arguments, substitute, JvmOptions, LaunchCommand,
+};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+#[test]
+fn values_with_spaces_stay_in_one_argument() {
+    let values = HashMap::from([
+        (
+            "natives_directory",
+            OsString::from("/home/a b/.minecraft/natives"),
+        ),
+        ("auth_player_name", OsString::from("Steve")),
+    ]);
+
+    assert_eq!(
+        substitute("-Djava.library.path=${natives_directory}", &values),
+        "-Djava.library.path=/home/a b/.minecraft/natives"
+    );
+    assert_eq!(substitute("${auth_player_name}", &values), "Steve");
+    // 没有对应值的占位符保持原样
+    assert_eq!(
+        substitute("--width ${resolution_width}", &values),
+        "--width ${resolution_width}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_values_are_preserved() {
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let values = HashMap::from([("game_directory", OsString::from_vec(b"/tmp/\xff".to_vec()))]);
+    let result = substitute("--gameDir=${game_directory}", &values);
+
+    assert_eq!(result.as_bytes(), b"--gameDir=/tmp/\xff");
+}
+
+#[test]
+fn display_quotes_arguments() {
+    let command = LaunchCommand {
+        program: "java".into(),
+        args: vec!["-cp".into(), "/a b/c.jar".into(), "it's".into()],
+        current_dir: None,
+    };
+
+    assert_eq!(command.to_string(), r"java -cp '/a b/c.jar' 'it'\''s'");
+
+    let command = LaunchCommand {
+        current_dir: Some(PathBuf::from("/games/my pack")),
+        ..command
+    };
+    assert_eq!(
+        command.to_string(),
+        r"cd '/games/my pack' && java -cp '/a b/c.jar' 'it'\''s'"
+    );
+}
+
+#[test]
+fn jvm_options_produce_memory_and_extra_arguments() {
+    let options = JvmOptions {
+        min_memory: Some(512),
+        max_memory: Some(4096),
+        auto_memory: false,
+        extra_arguments: split_arguments("-XX:+UseG1GC '-Dfoo=a b'").unwrap(),
+    };
+
+    assert_eq!(options.validate(16384), Ok(()));
+    assert_eq!(
+        options.arguments(16384),
+        ["-Xms512m", "-Xmx4096m", "-XX:+UseG1GC", "-Dfoo=a b"]
+    );
+
+    // 没有任何设置时使用 jvm 的默认值
+    assert!(JvmOptions::new().arguments(16384).is_empty());
+}
+
+#[test]
+fn auto_memory_follows_system_memory() {
+    assert_eq!(auto_memory(4096), 2048);
+    assert_eq!(auto_memory(1024), 1024);
+    assert_eq!(auto_memory(65536), 8192);
+
+    let options = JvmOptions {
+        auto_memory: true,
+        ..JvmOptions::new()
+    };
+    assert_eq!(options.arguments(16384), ["-Xmx8192m"]);
+
+    // 系统内存不足 1 GiB 时不超过系统内存，自动分配的值仍然有效
+    assert_eq!(auto_memory(768), 768);
+    assert_eq!(options.validate(768), Ok(()));
+    assert_eq!(options.arguments(768), ["-Xmx768m"]);
+
+    // 无法获取系统内存时按最小值分配
+    assert_eq!(auto_memory(0), 1024);
+    assert_eq!(options.validate(0), Ok(()));
+    assert_eq!(options.arguments(0), ["-Xmx1024m"]);
+}
+
+#[test]
+fn bad_jvm_options_are_rejected() {
+    let with = |min_memory, max_memory, extra: &str| JvmOptions {
+        min_memory,
+        max_memory,
+        auto_memory: false,
+        extra_arguments: split_arguments(extra).unwrap(),
+    };
+
+    assert!(with(None, Some(128), "").validate(16384).is_err());
+    assert!(with(None, Some(32768), "").validate(16384).is_err());
+    // 系统内存未知时不限制最大内存
+    assert!(with(None, Some(32768), "").validate(0).is_ok());
+    assert!(with(None, Some(128), "").validate(0).is_err());
+    assert!(with(Some(4096), Some(2048), "").validate(16384).is_err());
+    assert!(with(None, None, "nogui").validate(16384).is_err());
+    assert!(with(None, Some(2048), "-Xmx1G").validate(16384).is_err());
+    assert!(with(None, None, "-Xmx1G").validate(16384).is_ok());
+    assert!(split_arguments("-Dfoo='a").is_err());
+}
@@ -0,0 +1,102 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// 一个请求的记录。
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+/// 按路径返回固定响应的本地 HTTP 服务器。
+pub struct MockServer {
+    pub root: String,
+    pub requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    /// `routes`: 路径到 `(状态码, 响应内容)` 的映射，没有对应路径时返回 404。
+    ///
+    /// 同一个路径出现多次时按顺序依次返回，最后一个响应会一直重复。
+    pub fn start(routes: Vec<(&str, u16, String)>) -> MockServer {
+        MockServer::start_bytes(
+            routes
+                .into_iter()
+                .map(|(path, status, body)| (path, status, body.into_bytes()))
+                .collect(),
+        )
+    }
+
+    /// 与 `start` 相同，但响应内容可以是二进制数据。
+    pub fn start_bytes(routes: Vec<(&str, u16, Vec<u8>)>) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let mut queues: HashMap<String, VecDeque<(u16, Vec<u8>)>> = HashMap::new();
+        for (path, status, body) in routes {
+            queues
+                .entry(path.to_string())
+                .or_default()
+                .push_back((status, body));
+        }
+        let requests = Arc::new(Mutex::new(vec![]));
+        let log = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                log.lock().unwrap().push(Request {
+                    method,
+                    path: path.clone(),
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                });
+
+                let (status, body) = match queues.get_mut(&path) {
+                    Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+                    Some(queue) => queue[0].clone(),
+                    None => (404, vec![]),
+                };
+                let header = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
+
+        MockServer { root, requests }
+    }
+}
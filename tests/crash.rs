@@ -0,0 +1,169 @@
+use command_minecraft_launcher::crash::{
+    diagnose, find_crash_log, parse_sections, CrashAnalysis, CrashCause, CrashLog, CrashLogKind,
+};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+fn fixture(name: &str) -> CrashLog {
+    let path = Path::new("tests/fixtures/crash").join(name);
+    let kind = if name.starts_with("hs_err") {
+        CrashLogKind::JvmCrash
+    } else {
+        CrashLogKind::CrashReport
+    };
+
+    CrashLog::read(&path, kind).unwrap()
+}
+
+#[test]
+fn crash_report_is_split_into_sections() {
+    let log = fixture("crash-report.txt");
+    let titles: Vec<&str> = log.sections.iter().map(|x| x.title.as_str()).collect();
+    assert_eq!(titles, ["", "Head", "System Details"]);
+
+    assert!(log.sections[0]
+        .body
+        .starts_with("---- Minecraft Crash Report ----"));
+    assert!(log
+        .section("System Details")
+        .unwrap()
+        .body
+        .contains("Java Version: 17.0.8"));
+
+    let log = fixture("hs_err_pid1234.log");
+    let titles: Vec<&str> = log.sections.iter().map(|x| x.title.as_str()).collect();
+    assert_eq!(titles, ["", "SUMMARY", "THREAD"]);
+
+    // 没有节标题时整个日志是一节
+    let sections = parse_sections("just one line\n", CrashLogKind::CrashReport);
+    assert_eq!(sections.len(), 1);
+    assert_eq!(sections[0].body, "just one line");
+}
+
+#[test]
+fn known_causes_are_diagnosed() {
+    let cases = [
+        (
+            "Exception in thread \"main\" java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 61.0)",
+            CrashCause::WrongJavaVersion,
+        ),
+        (
+            "java.lang.UnsatisfiedLinkError: no lwjgl64 in java.library.path",
+            CrashCause::MissingNativeLibrary,
+        ),
+        (
+            "java.lang.OutOfMemoryError: Java heap space",
+            CrashCause::OutOfMemory,
+        ),
+        (
+            "net.fabricmc.loader.impl.discovery.ModResolutionException: Incompatible mods found!",
+            CrashCause::ModConflict,
+        ),
+        (
+            "GLFW error 65542: WGL: The driver does not appear to support OpenGL",
+            CrashCause::GraphicsDriver,
+        ),
+        (
+            "# C  [ig9icd64.dll+0x4c2f1]",
+            CrashCause::GraphicsDriver,
+        ),
+    ];
+
+    for (line, cause) in cases {
+        let diagnoses = diagnose(&format!("[12:00:00] start\n{}\n", line));
+        assert_eq!(diagnoses.len(), 1, "{}", line);
+        assert_eq!(diagnoses[0].cause, cause);
+        assert_eq!(diagnoses[0].evidence, line.trim());
+    }
+
+    assert!(diagnose("[12:00:00] [Render thread/INFO]: Stopping!").is_empty());
+}
+
+#[test]
+fn analysis_combines_crash_log_and_output() {
+    let analysis = CrashAnalysis::new(
+        Some(1),
+        Some(fixture("crash-report.txt")),
+        "java.lang.OutOfMemoryError: Java heap space\nMixin apply failed",
+    );
+
+    let causes: Vec<CrashCause> = analysis.diagnoses.iter().map(|x| x.cause).collect();
+    assert_eq!(causes, [CrashCause::ModConflict, CrashCause::OutOfMemory]);
+
+    let text = analysis.to_string();
+    assert!(text.contains("退出代码 1"));
+    assert!(text.contains("描述: Mod loading error has occurred"));
+    assert!(text.contains("模组冲突"));
+
+    let json: serde_json::Value = serde_json::from_str(&analysis.to_json().unwrap()).unwrap();
+    assert_eq!(json["exit_code"], 1);
+    assert_eq!(json["crash_log"]["kind"], "crash_report");
+    assert_eq!(json["crash_log"]["sections"][1]["title"], "Head");
+    assert_eq!(json["crash_log"]["sections"][2]["title"], "System Details");
+    assert!(json["crash_log"].get("content").is_none());
+    assert_eq!(json["diagnoses"][0]["cause"], "mod_conflict");
+    assert_eq!(json["diagnoses"][1]["cause"], "out_of_memory");
+
+    let empty = CrashAnalysis::new(None, None, "");
+    assert!(empty.to_string().contains("没有找到已知的原因"));
+}
+
+#[test]
+fn analysis_is_saved_for_scripts() {
+    let path = std::env::temp_dir()
+        .join(format!("cml-crash-json-{}", std::process::id()))
+        .join("crash-analysis.json");
+    let analysis = CrashAnalysis::new(Some(1), Some(fixture("crash-report.txt")), "");
+
+    analysis.save(&path).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::from_str::<serde_json::Value>(&analysis.to_json().unwrap()).unwrap()
+    );
+    assert!(json["crash_log"]["path"]
+        .as_str()
+        .unwrap()
+        .ends_with("crash-report.txt"));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_crash_log_path_is_serialized() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut log = fixture("crash-report.txt");
+    log.path = Path::new(OsStr::from_bytes(b"/tmp/\xff/crash.txt")).to_path_buf();
+    let analysis = CrashAnalysis::new(Some(1), Some(log), "");
+
+    let json: serde_json::Value = serde_json::from_str(&analysis.to_json().unwrap()).unwrap();
+    assert_eq!(json["crash_log"]["path"], "/tmp/\u{fffd}/crash.txt");
+}
+
+#[test]
+fn newest_crash_log_since_launch_is_found() {
+    let game_dir = std::env::temp_dir().join(format!("cml-crash-{}", std::process::id()));
+    let reports = game_dir.join("crash-reports");
+    std::fs::create_dir_all(&reports).unwrap();
+
+    let since = SystemTime::now() - Duration::from_secs(60);
+    assert!(find_crash_log(&game_dir, since).is_none());
+
+    std::fs::copy(
+        "tests/fixtures/crash/crash-report.txt",
+        reports.join("crash-2023-07-22_12.00.00-client.txt"),
+    )
+    .unwrap();
+    std::fs::write(reports.join("notes.txt"), "not a crash report").unwrap();
+
+    let log = find_crash_log(&game_dir, since).unwrap();
+    assert_eq!(log.kind, CrashLogKind::CrashReport);
+    assert!(log.path.ends_with("crash-2023-07-22_12.00.00-client.txt"));
+
+    // 启动之前的崩溃报告不算
+    assert!(find_crash_log(&game_dir, SystemTime::now() + Duration::from_secs(60)).is_none());
+
+    std::fs::remove_dir_all(&game_dir).unwrap();
+}
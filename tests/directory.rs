@@ -0,0 +1,115 @@
+use command_minecraft_launcher::directory::{GameDirMode, GameDirectory};
+use command_minecraft_launcher::instance::Instance;
+use command_minecraft_launcher::minecraft_core::{load_version, Launcher};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn paths_are_absolute_and_typed() {
+    let directory = GameDirectory::new("game");
+    let root = std::env::current_dir().unwrap().join("game");
+    assert_eq!(directory.root, root);
+
+    assert_eq!(
+        directory.version_json("1.19"),
+        root.join("versions/1.19/1.19.json")
+    );
+    assert_eq!(
+        directory.version_jar("1.19"),
+        root.join("versions/1.19/1.19.jar")
+    );
+    assert_eq!(
+        directory.natives("1.19"),
+        root.join("versions/1.19/natives")
+    );
+    assert_eq!(
+        directory.library("org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"),
+        root.join("libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar")
+    );
+    assert_eq!(
+        directory.asset_index("1.19"),
+        root.join("assets/indexes/1.19.json")
+    );
+    assert_eq!(
+        directory.asset_object("ab/abcdef"),
+        root.join("assets/objects/ab/abcdef")
+    );
+    assert_eq!(
+        directory.virtual_assets("legacy"),
+        root.join("assets/virtual/legacy")
+    );
+    assert_eq!(directory.runtime(), root.join("runtime"));
+    assert_eq!(
+        directory.launcher_log(),
+        root.join("logs/launcher/latest.log")
+    );
+    assert_eq!(
+        directory.crash_analysis(),
+        root.join("logs/launcher/crash-analysis.json")
+    );
+}
+
+#[test]
+fn versions_are_loaded_from_the_given_directory() {
+    let root = std::env::temp_dir().join(format!("cml-directory-{}", std::process::id()));
+    let directory = GameDirectory::new(&root);
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    for name in ["1.19", "fabric-loader-0.14.21-1.19"] {
+        std::fs::create_dir_all(directory.version_dir(name)).unwrap();
+        std::fs::copy(
+            fixtures.join(format!("{}.json", name)),
+            directory.version_json(name),
+        )
+        .unwrap();
+    }
+
+    let version = load_version(&directory, "fabric-loader-0.14.21-1.19").unwrap();
+    assert_eq!(version.jar.as_deref(), Some("1.19"));
+    assert_eq!(version.assets.as_deref(), Some("1.19"));
+
+    let err = load_version(&GameDirectory::new(root.join("empty")), "1.19").unwrap_err();
+    assert!(err.contains(&root.join("empty").display().to_string()));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn game_directory_follows_instance_settings() {
+    let root = std::env::temp_dir().join(format!("cml-instance-{}", std::process::id()));
+    let directory = GameDirectory::new(&root);
+    let launcher = Launcher::new().with_directory(directory.clone());
+
+    // 没有设置文件时与其他版本共用根文件夹
+    assert_eq!(
+        Instance::load(&directory, "pack").unwrap().game_directory,
+        GameDirMode::Shared
+    );
+    assert_eq!(launcher.game_directory("pack").unwrap(), root);
+
+    let mut settings = Instance {
+        game_directory: GameDirMode::Isolated,
+        ..Instance::new("pack", "1.19")
+    };
+    settings.save(&directory).unwrap();
+    assert_eq!(Instance::load(&directory, "pack").unwrap(), settings);
+    assert_eq!(
+        launcher.game_directory("pack").unwrap(),
+        root.join("versions/pack")
+    );
+    // 其他版本不受影响
+    assert_eq!(launcher.game_directory("vanilla").unwrap(), root);
+
+    settings.game_directory = GameDirMode::Custom {
+        path: PathBuf::from("instances/pack"),
+    };
+    settings.save(&directory).unwrap();
+    assert_eq!(
+        launcher.game_directory("pack").unwrap(),
+        root.join("instances/pack")
+    );
+
+    let text = std::fs::read_to_string(directory.instance_settings("pack")).unwrap();
+    assert!(text.contains(r#""mode": "custom""#));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,206 @@
+use command_minecraft_launcher::downloader::downloader::{DownloadTask, Scheduler};
+use command_minecraft_launcher::verify::sha1_bytes;
+use indicatif::ProgressBar;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cml-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn scheduler(concurrency: usize, retries: u32, backoff: Duration) -> Scheduler {
+    Scheduler {
+        concurrency,
+        retries,
+        backoff,
+    }
+}
+
+/// 测试用的服务器，每个请求延迟 `delay` 后响应。
+///
+/// `/missing` 开头的路径返回 404，`/flaky` 开头的路径第一次请求返回 500，
+/// 其他路径的响应内容为 `file <路径>`。
+struct Server {
+    root: String,
+    /// 同时处理的最大请求数
+    max: Arc<AtomicUsize>,
+    /// 按顺序记录的请求路径
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    fn start(delay: Duration) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        let active = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let server = Server {
+            root,
+            max: max.clone(),
+            requests: requests.clone(),
+        };
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let active = active.clone();
+                let max = max.clone();
+                let requests = requests.clone();
+
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let path = request_line
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_string();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+
+                    let seen = {
+                        let mut requests = requests.lock().unwrap();
+                        let seen = requests.iter().filter(|x| **x == path).count();
+                        requests.push(path.clone());
+                        seen
+                    };
+
+                    let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(current, Ordering::SeqCst);
+                    thread::sleep(delay);
+                    active.fetch_sub(1, Ordering::SeqCst);
+
+                    let status = if path.starts_with("/missing") {
+                        404
+                    } else if path.starts_with("/flaky") && seen == 0 {
+                        500
+                    } else {
+                        200
+                    };
+                    let body = format!("file {}", path);
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                });
+            }
+        });
+
+        server
+    }
+
+    /// 下载 `name` 到 `dir` 中的任务，按服务器的响应内容校验。
+    fn task(&self, dir: &Path, name: &str) -> DownloadTask {
+        let content = format!("file /{}", name);
+
+        DownloadTask::new(
+            dir.join(name).to_string_lossy().into_owned(),
+            format!("{}/{}", self.root, name),
+        )
+        .checked(&sha1_bytes(content.as_bytes()), content.len() as u64)
+    }
+
+    /// 对 `path` 的请求次数。
+    fn count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| *x == path)
+            .count()
+    }
+}
+
+#[tokio::test]
+async fn tasks_are_limited_by_concurrency() {
+    for concurrency in [1, 2] {
+        let server = Server::start(Duration::from_millis(100));
+        let dir = temp_dir(&format!("scheduler-concurrency-{}", concurrency));
+        let tasks: Vec<DownloadTask> = (0..6).map(|x| server.task(&dir, &x.to_string())).collect();
+
+        let progress_bar = ProgressBar::hidden();
+        let failures = scheduler(concurrency, 0, Duration::ZERO)
+            .run(tasks, &progress_bar)
+            .await;
+
+        assert!(failures.is_empty());
+        assert_eq!(progress_bar.position(), 6);
+        assert_eq!(server.max.load(Ordering::SeqCst), concurrency);
+        assert_eq!(std::fs::read_to_string(dir.join("5")).unwrap(), "file /5");
+    }
+}
+
+#[tokio::test]
+async fn failed_downloads_are_retried_with_backoff() {
+    let server = Server::start(Duration::ZERO);
+    let dir = temp_dir("scheduler-retry");
+
+    let failures = scheduler(4, 3, Duration::from_millis(10))
+        .run(vec![server.task(&dir, "flaky")], &ProgressBar::hidden())
+        .await;
+    assert!(failures.is_empty());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("flaky")).unwrap(),
+        "file /flaky"
+    );
+    assert_eq!(server.count("/flaky"), 2);
+}
+
+#[tokio::test]
+async fn permanent_failures_are_reported() {
+    let server = Server::start(Duration::ZERO);
+    let dir = temp_dir("scheduler-failure");
+    let tasks = vec![server.task(&dir, "ok"), server.task(&dir, "missing")];
+
+    let progress_bar = ProgressBar::hidden();
+    let started = Instant::now();
+    let failures = scheduler(2, 2, Duration::from_millis(50))
+        .run(tasks, &progress_bar)
+        .await;
+
+    // 两次重试分别等待 50ms 和 100ms
+    assert!(started.elapsed() >= Duration::from_millis(150));
+    assert_eq!(progress_bar.position(), 2);
+
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].task.url, format!("{}/missing", server.root));
+    assert!(!failures[0].reason.is_empty());
+
+    assert!(server.count("/missing") >= 3);
+    assert_eq!(std::fs::read_to_string(dir.join("ok")).unwrap(), "file /ok");
+}
+
+#[tokio::test]
+async fn many_retries_do_not_overflow_backoff() {
+    let server = Server::start(Duration::ZERO);
+    let dir = temp_dir("scheduler-many-retries");
+
+    // 等待时间按 2^重试次数 增长，超过 u32 时不能溢出
+    let failures = scheduler(1, 40, Duration::ZERO)
+        .run(vec![server.task(&dir, "missing")], &ProgressBar::hidden())
+        .await;
+
+    assert_eq!(failures.len(), 1);
+    assert!(server.count("/missing") >= 41);
+}
@@ -0,0 +1,218 @@
+use command_minecraft_launcher::directory::{GameDirMode, GameDirectory};
+use command_minecraft_launcher::instance::{
+    dependents, detect_loader, inspect_version, installed_versions, validate_name, Instance,
+    LoaderKind, ModLoader, VersionStatus, WindowSize,
+};
+use command_minecraft_launcher::minecraft_core::load_version;
+use command_minecraft_launcher::model::GameVersionType;
+use std::path::{Path, PathBuf};
+
+/// 在临时文件夹中安装 1.19 和继承它的 fabric 版本。
+fn install(name: &str) -> (PathBuf, GameDirectory) {
+    let root = std::env::temp_dir().join(format!("cml-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let directory = GameDirectory::new(&root);
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    for name in ["1.19", "fabric-loader-0.14.21-1.19"] {
+        std::fs::create_dir_all(directory.version_dir(name)).unwrap();
+        std::fs::copy(
+            fixtures.join(format!("{}.json", name)),
+            directory.version_json(name),
+        )
+        .unwrap();
+    }
+    std::fs::write(directory.version_jar("1.19"), b"jar").unwrap();
+
+    (root, directory)
+}
+
+#[test]
+fn settings_are_saved_and_loaded() {
+    let (root, directory) = install("instance-settings");
+
+    // 没有设置文件时，游戏版本取自继承的版本
+    let instance = Instance::load(&directory, "fabric-loader-0.14.21-1.19").unwrap();
+    assert_eq!(instance.name, "fabric-loader-0.14.21-1.19");
+    assert_eq!(instance.game_version, "1.19");
+    assert_eq!(instance.game_directory, GameDirMode::Shared);
+
+    let mut instance = Instance::create(&directory, "fabric-loader-0.14.21-1.19", "1.19").unwrap();
+    assert!(instance.created > 0);
+    assert!(Instance::create(&directory, "fabric-loader-0.14.21-1.19", "1.19").is_err());
+    assert!(Instance::create(&directory, "missing", "1.19").is_err());
+
+    instance.loader = Some(ModLoader {
+        kind: LoaderKind::Fabric,
+        version: String::from("0.14.21"),
+    });
+    instance.max_memory = Some(4096);
+    instance.jvm_arguments = vec![String::from("-XX:+UseG1GC")];
+    instance.window = Some(WindowSize {
+        width: 1280,
+        height: 720,
+    });
+    instance.notes = String::from("生存");
+    instance.mark_played(&directory).unwrap();
+
+    let loaded = Instance::load(&directory, "fabric-loader-0.14.21-1.19").unwrap();
+    assert_eq!(loaded, instance);
+    assert!(loaded.last_played.is_some());
+    assert_eq!(loaded.jvm_options().max_memory, Some(4096));
+    assert_eq!(loaded.jvm_options().extra_arguments, ["-XX:+UseG1GC"]);
+
+    let text =
+        std::fs::read_to_string(directory.instance_settings("fabric-loader-0.14.21-1.19")).unwrap();
+    assert!(text.contains(r#""gameVersion": "1.19""#));
+    assert!(text.contains(r#""kind": "fabric""#));
+    assert!(!text.contains(r#""name""#));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn instances_are_cloned_renamed_and_deleted() {
+    let (root, directory) = install("instance-crud");
+
+    let mut vanilla = Instance::create(&directory, "1.19", "1.19").unwrap();
+    vanilla.game_directory = GameDirMode::Isolated;
+    vanilla.mark_played(&directory).unwrap();
+    std::fs::create_dir_all(directory.version_dir("1.19").join("saves/world")).unwrap();
+
+    // 复制时连同隔离的存档一起复制，json 和 jar 按新名称命名
+    let copy = vanilla.clone_to(&directory, "survival").unwrap();
+    assert_eq!(copy.name, "survival");
+    assert_eq!(copy.game_directory, GameDirMode::Isolated);
+    assert_eq!(copy.last_played, None);
+    assert!(directory.version_jar("survival").is_file());
+    assert!(directory
+        .version_dir("survival")
+        .join("saves/world")
+        .is_dir());
+    assert!(!directory.version_dir("survival").join("1.19.json").exists());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(directory.version_json("survival")).unwrap())
+            .unwrap();
+    assert_eq!(json["id"], "survival");
+    assert_eq!(Instance::load(&directory, "survival").unwrap(), copy);
+
+    assert!(vanilla.clone_to(&directory, "survival").is_err());
+    assert!(vanilla.clone_to(&directory, "a/b").is_err());
+
+    // 被 fabric 继承的版本不能重命名或删除
+    assert_eq!(
+        dependents(&directory, "1.19"),
+        ["fabric-loader-0.14.21-1.19"]
+    );
+    assert!(vanilla.rename(&directory, "vanilla").is_err());
+    assert!(vanilla.clone().delete(&directory).is_err());
+
+    let mut copy = copy;
+    copy.rename(&directory, "hardcore").unwrap();
+    assert_eq!(copy.name, "hardcore");
+    assert!(!directory.version_dir("survival").exists());
+    assert!(directory.version_jar("hardcore").is_file());
+    assert_eq!(Instance::load(&directory, "hardcore").unwrap(), copy);
+
+    copy.delete(&directory).unwrap();
+    assert!(!directory.version_dir("hardcore").exists());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn names_must_be_valid_folder_names() {
+    assert!(validate_name("1.19 modded").is_ok());
+    assert!(validate_name("").is_err());
+    assert!(validate_name("..").is_err());
+    assert!(validate_name(" pack").is_err());
+    assert!(validate_name("a:b").is_err());
+}
+
+#[test]
+fn installed_versions_are_listed() {
+    let (root, directory) = install("instance-list");
+    std::fs::create_dir_all(directory.version_dir("empty")).unwrap();
+
+    let versions = installed_versions(&directory);
+    let names: Vec<&str> = versions.iter().map(|x| x.instance.name.as_str()).collect();
+    assert_eq!(names, ["1.19", "empty", "fabric-loader-0.14.21-1.19"]);
+
+    let vanilla = &versions[0];
+    assert_eq!(vanilla.instance.game_version, "1.19");
+    assert_eq!(vanilla.version_type, Some(GameVersionType::Release));
+    assert_eq!(vanilla.loader, None);
+    assert_eq!(vanilla.java_version, Some(17));
+
+    // 库文件和资源索引还没有下载
+    let missing = match &vanilla.status {
+        VersionStatus::Incomplete { missing } => missing.clone(),
+        status => panic!("{:?}", status),
+    };
+    assert!(missing.contains(&directory.asset_index("1.19")));
+    assert!(!missing.contains(&directory.version_jar("1.19")));
+
+    for path in &missing {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+    assert_eq!(
+        inspect_version(&directory, "1.19").status,
+        VersionStatus::Complete
+    );
+
+    // 资源索引的文件名来自 `assets` 而不是 `assetIndex.id`
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(directory.version_json("1.19")).unwrap())
+            .unwrap();
+    json["assets"] = serde_json::Value::from("1.19-custom");
+    std::fs::write(directory.version_json("1.19"), json.to_string()).unwrap();
+    assert_eq!(
+        inspect_version(&directory, "1.19").status,
+        VersionStatus::Incomplete {
+            missing: vec![directory.asset_index("1.19-custom")]
+        }
+    );
+
+    assert!(matches!(versions[1].status, VersionStatus::Broken(_)));
+    assert_eq!(versions[1].java_version, None);
+
+    let fabric = &versions[2];
+    assert_eq!(fabric.instance.game_version, "1.19");
+    assert_eq!(
+        fabric.loader,
+        Some(ModLoader {
+            kind: LoaderKind::Fabric,
+            version: String::from("0.14.21"),
+        })
+    );
+    assert_eq!(fabric.java_version, Some(17));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn loader_is_detected_from_libraries() {
+    let (root, directory) = install("instance-loader");
+
+    let version = load_version(&directory, "fabric-loader-0.14.21-1.19").unwrap();
+    assert_eq!(
+        detect_loader(&version).map(|x| x.to_string()).as_deref(),
+        Some("Fabric 0.14.21")
+    );
+
+    let mut version = load_version(&directory, "1.19").unwrap();
+    assert_eq!(detect_loader(&version), None);
+
+    version.libraries[0].name = String::from("net.minecraftforge:forge:1.19-41.1.0");
+    assert_eq!(
+        detect_loader(&version),
+        Some(ModLoader {
+            kind: LoaderKind::Forge,
+            version: String::from("41.1.0"),
+        })
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
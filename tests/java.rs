@@ -0,0 +1,209 @@
+mod common;
+
+use command_minecraft_launcher::java::{major_version, parse_properties, select, JavaRuntime};
+use command_minecraft_launcher::minecraft_core::DownloadManager;
+use command_minecraft_launcher::model::JavaVersion;
+use command_minecraft_launcher::rules::Environment;
+use command_minecraft_launcher::verify::sha1_bytes;
+use common::MockServer;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn runtime(major_version: u32, arch: &str) -> JavaRuntime {
+    JavaRuntime {
+        path: PathBuf::from(format!("/jvm/{}-{}/bin/java", major_version, arch)),
+        version: major_version.to_string(),
+        major_version,
+        arch: arch.to_string(),
+    }
+}
+
+fn java_version(major_version: u32) -> JavaVersion {
+    JavaVersion {
+        component: String::from("java-runtime-gamma"),
+        major_version,
+    }
+}
+
+#[test]
+fn major_version_handles_old_and_new_schemes() {
+    assert_eq!(major_version("1.8.0_382"), Some(8));
+    assert_eq!(major_version("17.0.8"), Some(17));
+    assert_eq!(major_version("21"), Some(21));
+    assert_eq!(major_version("22-ea"), Some(22));
+    assert_eq!(major_version("unknown"), None);
+}
+
+#[test]
+fn properties_are_parsed() {
+    let output = "Property settings:\n    file.encoding = UTF-8\n    java.version = 17.0.8\n    java.version.date = 2023-07-18\n    os.arch = amd64\n\nopenjdk version \"17.0.8\" 2023-07-18\n";
+
+    assert_eq!(
+        parse_properties(output),
+        Some((String::from("17.0.8"), String::from("x86_64")))
+    );
+    assert_eq!(parse_properties("openjdk version \"17.0.8\""), None);
+}
+
+#[test]
+fn select_prefers_exact_major_version_and_arch() {
+    let env = Environment {
+        os_name: String::from("linux"),
+        os_arch: String::from("x86_64"),
+        os_version: String::from("6.1.0"),
+        features: HashMap::new(),
+    };
+    let runtimes = [
+        runtime(21, "x86_64"),
+        runtime(17, "x86"),
+        runtime(17, "x86_64"),
+        runtime(8, "x86_64"),
+    ];
+
+    let java_17 = select(&runtimes, Some(&java_version(17)), &env).unwrap();
+    assert_eq!(
+        (java_17.major_version, java_17.arch.as_str()),
+        (17, "x86_64")
+    );
+
+    // 没有 javaVersion 的旧版本使用 Java 8
+    assert_eq!(select(&runtimes, None, &env).unwrap().major_version, 8);
+
+    // 没有完全相同的版本时选择更高的版本中最低的
+    let java_16 = select(&runtimes, Some(&java_version(16)), &env).unwrap();
+    assert_eq!(java_16.major_version, 17);
+
+    assert!(select(&runtimes, Some(&java_version(25)), &env).is_none());
+
+    // 旧版本不能使用 Java 9 及以上，没有 Java 8 时需要下载 jre-legacy
+    let modern = [runtime(21, "x86_64"), runtime(17, "x86_64")];
+    assert!(select(&modern, None, &env).is_none());
+    assert!(select(&modern, Some(&java_version(8)), &env).is_none());
+    assert_eq!(
+        select(&modern, Some(&java_version(16)), &env)
+            .unwrap()
+            .major_version,
+        17
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn probe_reads_version_from_java() {
+    use command_minecraft_launcher::java::probe;
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("cml-java-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let java = dir.join("java");
+    std::fs::write(
+        &java,
+        "#!/bin/sh\necho '    java.version = 1.8.0_382' >&2\necho '    os.arch = aarch64' >&2\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&java, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let runtime = probe(&java).unwrap();
+    assert_eq!(runtime.major_version, 8);
+    assert_eq!(runtime.version, "1.8.0_382");
+    assert_eq!(runtime.arch, "arm64");
+}
+
+#[test]
+fn managed_runtime_is_downloaded_and_installed() {
+    let java = b"#!/bin/sh\n".to_vec();
+    let release = b"JAVA_VERSION=\"17.0.8\"\n".repeat(16);
+    let mut compressed = vec![];
+    lzma_rs::lzma_compress(&mut release.as_slice(), &mut compressed).unwrap();
+
+    let files = MockServer::start_bytes(vec![
+        ("/java", 200, java.clone()),
+        ("/release.lzma", 200, compressed.clone()),
+    ]);
+    let manifest = json!({
+        "files": {
+            "bin": { "type": "directory" },
+            "bin/java": {
+                "type": "file",
+                "executable": true,
+                "downloads": {
+                    "raw": { "sha1": sha1_bytes(&java), "size": java.len(), "url": format!("{}/java", files.root) },
+                },
+            },
+            "release": {
+                "type": "file",
+                "executable": false,
+                "downloads": {
+                    "raw": { "sha1": sha1_bytes(&release), "size": release.len(), "url": format!("{}/release", files.root) },
+                    "lzma": { "sha1": sha1_bytes(&compressed), "size": compressed.len(), "url": format!("{}/release.lzma", files.root) },
+                },
+            },
+            "legal/java.base": { "type": "link", "target": "../release" },
+        },
+    })
+    .to_string();
+
+    let manifest_server = MockServer::start(vec![("/manifest.json", 200, manifest.clone())]);
+    let index = |sha1: &str| {
+        json!({
+            "linux": {
+                "java-runtime-gamma": [{
+                    "manifest": { "sha1": sha1, "size": manifest.len(), "url": format!("{}/manifest.json", manifest_server.root) },
+                    "version": { "name": "17.0.8", "released": "2023-07-18T00:00:00+00:00" },
+                }],
+            },
+        })
+        .to_string()
+    };
+    let index_server = MockServer::start(vec![
+        ("/all.json", 200, index(&sha1_bytes(manifest.as_bytes()))),
+        ("/bad.json", 200, index(&"0".repeat(40))),
+    ]);
+
+    let runtime_dir = std::env::temp_dir().join(format!("cml-runtime-{}", std::process::id()));
+    let manager = DownloadManager::new();
+    let download = |index: &str, component: &str| {
+        manager.download_java_runtime_from(
+            &format!("{}/{}", index_server.root, index),
+            component,
+            "linux",
+            &runtime_dir,
+        )
+    };
+
+    let path = download("all.json", "java-runtime-gamma").unwrap();
+    let home = runtime_dir.join("java-runtime-gamma/linux/java-runtime-gamma");
+    assert_eq!(
+        path,
+        home.join("bin")
+            .join(if cfg!(windows) { "java.exe" } else { "java" })
+    );
+    assert_eq!(std::fs::read(&path).unwrap(), java);
+    assert_eq!(std::fs::read(home.join("release")).unwrap(), release);
+    assert!(!home.join("release.lzma").exists());
+    assert_eq!(
+        std::fs::read_to_string(runtime_dir.join("java-runtime-gamma/linux/.version")).unwrap(),
+        "17.0.8"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert_eq!(
+            std::fs::read(home.join("legal/java.base")).unwrap(),
+            release
+        );
+    }
+
+    // 已经安装的文件校验通过时不重新下载
+    let count = files.requests.lock().unwrap().len();
+    download("all.json", "java-runtime-gamma").unwrap();
+    assert_eq!(files.requests.lock().unwrap().len(), count);
+
+    assert!(download("bad.json", "java-runtime-gamma").is_err());
+    assert!(download("all.json", "java-runtime-delta").is_err());
+}
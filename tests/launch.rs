@@ -0,0 +1,293 @@
+use command_minecraft_launcher::account::Account;
+use command_minecraft_launcher::command::JvmOptions;
+use command_minecraft_launcher::directory::GameDirectory;
+use command_minecraft_launcher::instance::WindowSize;
+use command_minecraft_launcher::minecraft_core::{
+    load_version, version_tasks, LaunchInfo, Launcher,
+};
+use command_minecraft_launcher::rules::Environment;
+use command_minecraft_launcher::yggdrasil::AuthlibInjector;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// 在临时文件夹中安装一个测试版本，文件夹的路径中有空格。
+fn install(name: &str, versions: &[&str]) -> (PathBuf, GameDirectory) {
+    let root = std::env::temp_dir().join(format!("cml {} {}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let directory = GameDirectory::new(&root);
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+
+    for version in versions {
+        std::fs::create_dir_all(directory.version_dir(version)).unwrap();
+        std::fs::copy(
+            fixtures.join(format!("{}.json", version)),
+            directory.version_json(version),
+        )
+        .unwrap();
+    }
+
+    std::fs::create_dir_all(directory.assets().join("indexes")).unwrap();
+    for assets in ["1.12", "1.19"] {
+        std::fs::write(directory.asset_index(assets), r#"{"objects":{}}"#).unwrap();
+    }
+
+    (root, directory)
+}
+
+/// 写入一个 zip 文件。
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+
+    for (name, content) in entries {
+        zip.start_file(*name, zip::write::FileOptions::default())
+            .unwrap();
+        zip.write_all(content).unwrap();
+    }
+
+    zip.finish().unwrap();
+}
+
+/// 当前系统需要解压的本地库文件。
+fn native_jars(directory: &GameDirectory, name: &str) -> Vec<PathBuf> {
+    let env = Environment::current();
+
+    load_version(directory, name)
+        .unwrap()
+        .libraries
+        .iter()
+        .filter(|x| x.natives.is_some() && x.is_allowed(&env))
+        .filter_map(|x| x.classifier(&x.native_classifier(&env)?).cloned())
+        .map(|x| directory.library(&x.path))
+        .collect()
+}
+
+fn info(name: &str) -> LaunchInfo {
+    LaunchInfo {
+        account: Account::offline("Steve"),
+        version: String::new(),
+        name: name.to_string(),
+        demo: false,
+        main_class: None,
+        authlib_injector: None,
+        java: Some(PathBuf::from("/opt/java/bin/java")),
+        jvm: JvmOptions::new(),
+        window: None,
+        log_file: None,
+    }
+}
+
+#[test]
+fn natives_are_extracted_without_excluded_files() {
+    let (root, directory) = install("natives", &["1.12.2"]);
+    let launcher = Launcher::new().with_directory(directory.clone());
+    let jars = native_jars(&directory, "1.12.2");
+
+    // 本地库不存在或损坏时返回错误，而不是崩溃
+    if let Some(jar) = jars.first() {
+        let err = launcher.command(&info("1.12.2")).unwrap_err();
+        assert!(err.contains(&jar.display().to_string()), "{}", err);
+
+        std::fs::create_dir_all(jar.parent().unwrap()).unwrap();
+        std::fs::write(jar, b"not a zip").unwrap();
+        let err = launcher.command(&info("1.12.2")).unwrap_err();
+        assert!(err.contains(&jar.display().to_string()), "{}", err);
+    }
+
+    for jar in &jars {
+        write_zip(
+            jar,
+            &[
+                ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n"),
+                ("liblwjgl.so", b"native"),
+            ],
+        );
+    }
+
+    launcher.command(&info("1.12.2")).unwrap();
+
+    let natives = directory.natives("1.12.2");
+    if !jars.is_empty() {
+        assert_eq!(
+            std::fs::read(natives.join("liblwjgl.so")).unwrap(),
+            b"native"
+        );
+    }
+    assert!(!natives.join("META-INF").exists());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// 启动参数中 `-cp` 之后的 classpath。
+fn class_path(args: &[OsString]) -> Vec<PathBuf> {
+    let index = args.iter().position(|x| x == "-cp").unwrap();
+    std::env::split_paths(&args[index + 1]).collect()
+}
+
+#[test]
+fn maven_libraries_are_on_the_class_path() {
+    let (root, directory) = install("fabric", &["1.19", "fabric-loader-0.14.21-1.19"]);
+    let launcher = Launcher::new().with_directory(directory.clone());
+
+    let command = launcher
+        .command(&info("fabric-loader-0.14.21-1.19"))
+        .unwrap();
+    let class_path = class_path(&command.args);
+
+    assert!(class_path.contains(
+        &directory.library("net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar")
+    ));
+    assert!(class_path.contains(&directory.library(
+        "net/fabricmc/tiny-mappings-parser/0.3.0+build.17/tiny-mappings-parser-0.3.0+build.17.jar"
+    )));
+    // 游戏主文件来自被继承的版本
+    assert_eq!(class_path.last(), Some(&directory.version_jar("1.19")));
+
+    let main_class = command
+        .args
+        .iter()
+        .position(|x| x == "net.fabricmc.loader.impl.launch.knot.KnotClient");
+    assert!(main_class.is_some());
+
+    // 下载时使用同样的路径
+    let version = load_version(&directory, "fabric-loader-0.14.21-1.19").unwrap();
+    let tasks = version_tasks(
+        &directory,
+        "fabric-loader-0.14.21-1.19",
+        &version,
+        &Environment::current(),
+    )
+    .unwrap();
+    let loader = tasks
+        .iter()
+        .find(|(_, x)| x.url.ends_with("fabric-loader-0.14.21.jar"))
+        .unwrap();
+    assert_eq!(
+        loader.1.url,
+        "https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar"
+    );
+    assert_eq!(
+        PathBuf::from(&loader.1.path),
+        directory.library("net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar")
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+/// 写入当前系统需要的本地库文件。
+fn install_natives(directory: &GameDirectory, name: &str) {
+    for jar in native_jars(directory, name) {
+        write_zip(&jar, &[("liblwjgl.so", b"native")]);
+    }
+}
+
+/// 参数在启动参数中的位置。
+fn position(args: &[OsString], arg: &str) -> usize {
+    match args.iter().position(|x| x == arg) {
+        Some(result) => result,
+        None => panic!("{} 不在 {:?} 中", arg, args),
+    }
+}
+
+#[test]
+fn arguments_are_in_launch_order() {
+    let (root, directory) = install("arguments", &["1.19"]);
+    let launcher = Launcher::new().with_directory(directory.clone());
+    install_natives(&directory, "1.19");
+
+    // log4j 配置文件由 download_version 下载
+    let log_config = directory.version_dir("1.19").join("client-1.12.xml");
+    std::fs::write(&log_config, "<Configuration/>").unwrap();
+
+    let mut info = info("1.19");
+    info.jvm.extra_arguments = vec![String::from("-XX:+UseG1GC")];
+    info.window = Some(WindowSize {
+        width: 854,
+        height: 480,
+    });
+    info.authlib_injector = Some(AuthlibInjector {
+        jar: root.join("authlib injector.jar"),
+        api_root: String::from("https://example.com/api/yggdrasil/"),
+        metadata: String::from("{}"),
+    });
+
+    let command = launcher.command(&info).unwrap();
+    let args = &command.args;
+    assert_eq!(command.program, "/opt/java/bin/java");
+    assert_eq!(command.current_dir.as_ref(), Some(&root));
+
+    // authlib-injector 必须是第一个参数
+    assert_eq!(
+        args[0],
+        format!(
+            "-javaagent:{}=https://example.com/api/yggdrasil/",
+            root.join("authlib injector.jar").display()
+        )
+        .as_str()
+    );
+
+    // jvm 参数都在主类之前，额外的 jvm 参数紧挨着主类
+    let main_class = position(args, "net.minecraft.client.main.Main");
+    let log4j = position(
+        args,
+        &format!("-Dlog4j.configurationFile={}", log_config.display()),
+    );
+    assert!(log4j < main_class);
+    assert!(position(args, "-cp") < main_class);
+    assert_eq!(args[main_class - 1], "-XX:+UseG1GC");
+
+    // 含有空格的路径是一个完整的参数
+    assert_eq!(args[position(args, "--gameDir") + 1], root.as_os_str());
+    assert_eq!(
+        args[position(args, "--assetsDir") + 1],
+        directory.assets().as_os_str()
+    );
+    assert!(args.contains(&OsString::from(format!(
+        "-Djava.library.path={}",
+        directory.natives("1.19").display()
+    ))));
+    assert!(class_path(args).contains(&directory.version_jar("1.19")));
+
+    // 新版本的窗口大小来自 arguments.game 中的规则
+    assert_eq!(args[position(args, "--width") + 1], "854");
+    assert_eq!(args[position(args, "--height") + 1], "480");
+    assert!(main_class < position(args, "--width"));
+
+    // 显示时给含有空格的参数加上引号
+    assert!(command
+        .to_string()
+        .contains(&format!("'{}'", root.display())));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn legacy_versions_get_window_size_arguments() {
+    let (root, directory) = install("legacy", &["1.12.2"]);
+    let launcher = Launcher::new().with_directory(directory.clone());
+    install_natives(&directory, "1.12.2");
+
+    let command = launcher.command(&info("1.12.2")).unwrap();
+    assert!(!command.args.contains(&OsString::from("--width")));
+    assert_eq!(
+        command.args[position(&command.args, "--gameDir") + 1],
+        root.as_os_str()
+    );
+
+    let mut info = info("1.12.2");
+    info.window = Some(WindowSize {
+        width: 854,
+        height: 480,
+    });
+
+    let command = launcher.command(&info).unwrap();
+    let args = &command.args;
+    assert!(position(args, "net.minecraft.client.main.Main") < position(args, "--username"));
+    assert_eq!(
+        args[args.len() - 4..],
+        ["--width", "854", "--height", "480"]
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
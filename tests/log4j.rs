@@ -0,0 +1,90 @@
+use command_minecraft_launcher::log4j::{parse_event, Log4jParser, LogLevel, LogRecord};
+
+const EVENTS: &str = r#"[12:00:00] plain output before log4j starts
+<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1690000000000" level="INFO" thread="Render thread">
+  <log4j:Message><![CDATA[Setting user: Steve]]></log4j:Message>
+</log4j:Event>
+
+<log4j:Event logger="net.minecraft.server.Main" timestamp="1690000001000" level="ERROR" thread="Server thread">
+  <log4j:Message><![CDATA[Encountered an unexpected exception]]></log4j:Message>
+  <log4j:Throwable><![CDATA[java.lang.NullPointerException: &lt;null&gt;
+	at net.minecraft.server.Main.main(Main.java:1)
+]]></log4j:Throwable>
+</log4j:Event>
+"#;
+
+fn parse_all(text: &str) -> Vec<LogRecord> {
+    let mut parser = Log4jParser::new();
+    let mut result: Vec<LogRecord> = text.lines().filter_map(|x| parser.push(x)).collect();
+    result.extend(parser.finish());
+    result
+}
+
+#[test]
+fn events_are_parsed_into_records() {
+    let records = parse_all(EVENTS);
+    assert_eq!(records.len(), 4);
+
+    assert_eq!(
+        records[0],
+        LogRecord::plain("[12:00:00] plain output before log4j starts")
+    );
+
+    let info = &records[1];
+    assert_eq!(info.level, Some(LogLevel::Info));
+    assert_eq!(info.thread, "Render thread");
+    assert_eq!(info.logger, "net.minecraft.client.Minecraft");
+    assert_eq!(info.message, "Setting user: Steve");
+    assert_eq!(info.time.unwrap().timestamp_millis(), 1690000000000);
+    assert_eq!(info.throwable, None);
+
+    // 事件之间的空行
+    assert_eq!(records[2], LogRecord::plain(""));
+
+    let error = &records[3];
+    assert_eq!(error.level, Some(LogLevel::Error));
+    assert_eq!(
+        error.throwable.as_deref(),
+        Some("java.lang.NullPointerException: &lt;null&gt;\n\tat net.minecraft.server.Main.main(Main.java:1)")
+    );
+    assert!(error.to_string().contains("[Server thread/ERROR]: Encountered an unexpected exception\njava.lang.NullPointerException"));
+}
+
+#[test]
+fn escaped_text_and_broken_events() {
+    let record = parse_event(
+        r#"<log4j:Event logger="a" timestamp="0" level="warn" thread="main"><log4j:Message>a &lt; b</log4j:Message></log4j:Event>"#,
+    )
+    .unwrap();
+    assert_eq!(record.level, Some(LogLevel::Warn));
+    assert_eq!(record.message, "a < b");
+
+    // 无法解析的事件按原样保留
+    let mut parser = Log4jParser::new();
+    let broken = r#"<log4j:Event level="INFO"><log4j:Message>oops</log4j:Event>"#;
+    assert_eq!(parser.push(broken), Some(LogRecord::plain(broken)));
+
+    // 游戏退出时没有结束的事件
+    assert_eq!(parser.push("<log4j:Event level=\"INFO\">"), None);
+    assert_eq!(
+        parser.finish(),
+        Some(LogRecord::plain("<log4j:Event level=\"INFO\">"))
+    );
+    assert_eq!(parser.finish(), None);
+}
+
+#[test]
+fn records_are_filtered_by_level_and_keyword() {
+    let records = parse_all(EVENTS);
+
+    let warnings: Vec<&LogRecord> = records
+        .iter()
+        .filter(|x| x.matches(LogLevel::Warn, ""))
+        .collect();
+    // 没有级别的输出总是显示
+    assert_eq!(warnings.len(), 3);
+
+    assert!(records[3].matches(LogLevel::Info, "NullPointerException"));
+    assert!(records[1].matches(LogLevel::Info, "Minecraft"));
+    assert!(!records[1].matches(LogLevel::Info, "Server"));
+}
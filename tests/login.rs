@@ -0,0 +1,195 @@
+mod common;
+
+use command_minecraft_launcher::auth::{request_device_code, AuthEndpoints, Login, LoginError};
+use common::MockServer;
+use serde_json::json;
+use std::sync::atomic::AtomicBool;
+
+fn routes(xsts: (u16, String), items: &[&str]) -> Vec<(&'static str, u16, String)> {
+    let items: Vec<_> = items.iter().map(|x| json!({ "name": x })).collect();
+
+    vec![
+        (
+            "/oauth20_token.srf",
+            200,
+            json!({
+                "access_token": "microsoft-token",
+                "refresh_token": "refresh-token",
+                "expires_in": 3600,
+            })
+            .to_string(),
+        ),
+        (
+            "/user/authenticate",
+            200,
+            json!({
+                "Token": "xbl-token",
+                "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] },
+            })
+            .to_string(),
+        ),
+        ("/xsts/authorize", xsts.0, xsts.1),
+        (
+            "/authentication/login_with_xbox",
+            200,
+            json!({ "access_token": "minecraft-token" }).to_string(),
+        ),
+        (
+            "/entitlements/mcstore",
+            200,
+            json!({ "items": items }).to_string(),
+        ),
+        (
+            "/minecraft/profile",
+            200,
+            json!({ "id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch" }).to_string(),
+        ),
+    ]
+}
+
+fn xsts_ok() -> (u16, String) {
+    (
+        200,
+        json!({
+            "Token": "xsts-token",
+            "DisplayClaims": { "xui": [{ "uhs": "user-hash" }] },
+        })
+        .to_string(),
+    )
+}
+
+#[tokio::test]
+async fn login_chain_returns_profile() {
+    let server = MockServer::start(routes(xsts_ok(), &["product_minecraft", "game_minecraft"]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+
+    let login = Login::login_from_microsoft_with("code", &endpoints)
+        .await
+        .unwrap();
+
+    assert!(login.is_logged());
+    assert_eq!(login.name(), "Notch");
+    assert_eq!(login.uuid(), "069a79f444e94726a5befca90e38aaf5");
+    assert_eq!(login.access_token(), "minecraft-token");
+    assert_eq!(login.refresh_token(), "refresh-token");
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests[0].body.contains("code=code"));
+    assert!(requests[1].body.contains("d=microsoft-token"));
+    assert!(requests[3].body.contains("XBL3.0 x=user-hash;xsts-token"));
+}
+
+#[tokio::test]
+async fn xsts_error_code_is_reported() {
+    let xsts = (
+        401,
+        json!({ "XErr": 2148916233u64, "Message": "" }).to_string(),
+    );
+    let server = MockServer::start(routes(xsts, &["game_minecraft"]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+
+    let err = Login::login_from_microsoft_with("code", &endpoints)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, LoginError::Xsts(2148916233)));
+}
+
+#[tokio::test]
+async fn account_without_game_is_rejected() {
+    let server = MockServer::start(routes(xsts_ok(), &[]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+
+    let err = Login::login_from_microsoft_with("code", &endpoints)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, LoginError::NotOwned));
+}
+
+fn device_code_routes(expires_in: u64, polls: &[(u16, &str)]) -> Vec<(&'static str, u16, String)> {
+    let mut result = vec![(
+        "/oauth20_connect.srf",
+        200,
+        json!({
+            "user_code": "ABCD1234",
+            "device_code": "device-code",
+            "verification_uri": "https://www.microsoft.com/link",
+            "expires_in": expires_in,
+            "interval": 0,
+        })
+        .to_string(),
+    )];
+
+    for (status, error) in polls {
+        result.push((
+            "/oauth20_token.srf",
+            *status,
+            json!({ "error": error }).to_string(),
+        ));
+    }
+
+    result.extend(routes(xsts_ok(), &["game_minecraft"]));
+    result
+}
+
+#[tokio::test]
+async fn device_code_login_polls_until_authorized() {
+    let server = MockServer::start(device_code_routes(
+        900,
+        &[
+            (400, "authorization_pending"),
+            (400, "authorization_pending"),
+        ],
+    ));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+
+    let device_code = request_device_code(&endpoints).await.unwrap();
+    assert_eq!(device_code.user_code, "ABCD1234");
+
+    let login = Login::login_from_device_code(&device_code, &endpoints, &AtomicBool::new(false))
+        .await
+        .unwrap();
+    assert_eq!(login.name(), "Notch");
+
+    let requests = server.requests.lock().unwrap();
+    let polls: Vec<_> = requests
+        .iter()
+        .filter(|x| x.path == "/oauth20_token.srf")
+        .collect();
+    assert_eq!(polls.len(), 3);
+    assert!(polls[0].body.contains("device_code=device-code"));
+}
+
+#[tokio::test]
+async fn device_code_declined_and_expired() {
+    let server = MockServer::start(device_code_routes(900, &[(400, "authorization_declined")]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+    let device_code = request_device_code(&endpoints).await.unwrap();
+
+    let err = Login::login_from_device_code(&device_code, &endpoints, &AtomicBool::new(false))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LoginError::DeviceCodeDeclined));
+
+    let server = MockServer::start(device_code_routes(900, &[(400, "expired_token")]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+    let device_code = request_device_code(&endpoints).await.unwrap();
+
+    let err = Login::login_from_device_code(&device_code, &endpoints, &AtomicBool::new(false))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LoginError::DeviceCodeExpired));
+}
+
+#[tokio::test]
+async fn device_code_login_can_be_cancelled() {
+    let server = MockServer::start(device_code_routes(900, &[(400, "authorization_pending")]));
+    let endpoints = AuthEndpoints::with_root(&server.root);
+    let device_code = request_device_code(&endpoints).await.unwrap();
+
+    let err = Login::login_from_device_code(&device_code, &endpoints, &AtomicBool::new(true))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LoginError::Cancelled));
+}
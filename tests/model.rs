@@ -0,0 +1,118 @@
+use command_minecraft_launcher::model::{self, AssetIndexFile, ModelError, VersionJson};
+
+fn fixture(name: &str) -> VersionJson {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    model::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn parse_error_names_the_field() {
+    let err = model::from_str::<VersionJson>(
+        r#"{"id": "1.19", "mainClass": "Main", "libraries": [{"name": 1}]}"#,
+    )
+    .unwrap_err();
+
+    match err {
+        ModelError::Parse { field, .. } => assert_eq!(field, "libraries[0].name"),
+        err => panic!("{}", err),
+    }
+}
+
+#[test]
+fn inherited_version_merges_with_parent() {
+    let child = fixture("fabric-loader-0.14.21-1.19.json");
+    let parent = fixture("1.19.json");
+    let parent_libraries = parent.libraries.len();
+
+    let merged = child.merge(parent);
+
+    assert_eq!(merged.id, "fabric-loader-0.14.21-1.19");
+    assert_eq!(
+        merged.main_class.as_deref(),
+        Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+    );
+    assert_eq!(merged.inherits_from, None);
+    assert_eq!(merged.assets.as_deref(), Some("1.19"));
+    assert_eq!(merged.libraries.len(), parent_libraries + 2);
+    assert_eq!(
+        merged.libraries[0].name,
+        "net.fabricmc:tiny-mappings-parser:0.3.0+build.17"
+    );
+
+    let jvm = &merged.arguments.unwrap().jvm;
+    assert!(matches!(
+        jvm.last().unwrap(),
+        model::Argument::Plain(x) if x.starts_with("-DFabricMcEmu=")
+    ));
+}
+
+#[test]
+fn maven_libraries_are_located_by_name() {
+    assert_eq!(
+        model::maven_path("net.fabricmc:fabric-loader:0.14.21").as_deref(),
+        Some("net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar")
+    );
+    assert_eq!(
+        model::maven_path("org.lwjgl:lwjgl:3.3.1:natives-linux").as_deref(),
+        Some("org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar")
+    );
+    assert_eq!(
+        model::maven_path("de.oceanlabs.mcp:mcp_config:1.19-20220607@zip").as_deref(),
+        Some("de/oceanlabs/mcp/mcp_config/1.19-20220607/mcp_config-1.19-20220607.zip")
+    );
+    assert_eq!(model::maven_path("broken"), None);
+
+    let fabric = fixture("fabric-loader-0.14.21-1.19.json");
+    let loader = fabric.libraries[1].file().unwrap();
+    assert_eq!(
+        loader.url,
+        "https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.14.21/fabric-loader-0.14.21.jar"
+    );
+    assert_eq!(loader.sha1, None);
+
+    // 没有 url 时使用 Mojang 的仓库
+    let mut library = fabric.libraries[1].clone();
+    library.url = None;
+    assert!(library
+        .file()
+        .unwrap()
+        .url
+        .starts_with(model::DEFAULT_LIBRARY_URL));
+
+    // 有 downloads 但只有本地库的库没有主文件
+    let legacy = fixture("1.12.2.json");
+    let platform = legacy
+        .libraries
+        .iter()
+        .find(|x| x.name.starts_with("org.lwjgl.lwjgl:lwjgl-platform:"))
+        .unwrap();
+    assert_eq!(platform.file(), None);
+    let vanilla = legacy.libraries[0].file().unwrap();
+    assert_eq!(
+        vanilla.sha1.as_deref(),
+        Some(legacy.libraries[0].artifact().unwrap().sha1.as_str())
+    );
+}
+
+#[test]
+fn invalid_asset_hash_is_rejected() {
+    let index = |hash: &str| {
+        model::from_str::<AssetIndexFile>(&format!(
+            r#"{{"objects": {{"icons/icon.png": {{"hash": "{}", "size": 1}}}}}}"#,
+            hash
+        ))
+    };
+
+    let object = &index("bdf48ef6b5d0d23bbb02e17d04865216179f510a")
+        .unwrap()
+        .objects["icons/icon.png"];
+    assert_eq!(object.path(), "bd/bdf48ef6b5d0d23bbb02e17d04865216179f510a");
+
+    // 格式错误的哈希不能用来拼接路径
+    for hash in ["", "b", "é0", "../../../../etc/passwd"] {
+        match index(hash).unwrap_err() {
+            ModelError::Parse { field, .. } => assert_eq!(field, "objects.icons/icon.png.hash"),
+            err => panic!("{}", err),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(unix)]
+
+use command_minecraft_launcher::command::LaunchCommand;
+use command_minecraft_launcher::process::{GameProcess, LogLine, LogSource, LOG_BUFFER_LINES};
+use std::ffi::OsString;
+
+fn shell(script: &str) -> LaunchCommand {
+    LaunchCommand {
+        program: OsString::from("sh"),
+        args: vec![OsString::from("-c"), OsString::from(script)],
+        current_dir: None,
+    }
+}
+
+#[test]
+fn output_and_exit_status_are_captured() {
+    let log_file = std::env::temp_dir()
+        .join(format!("cml-process-{}", std::process::id()))
+        .join("latest.log");
+
+    let mut game = GameProcess::spawn(
+        &shell("echo 'Setting user: Steve'; echo 'crashed' >&2; printf 'no newline'; exit 3"),
+        Some(&log_file),
+    )
+    .unwrap();
+
+    let status = game.wait().unwrap();
+    assert_eq!(status.code(), Some(3));
+
+    let mut lines: Vec<LogLine> = game.lines().collect();
+    lines.sort_by_key(|x| x.source == LogSource::Stderr);
+    assert_eq!(
+        lines,
+        [
+            LogLine {
+                source: LogSource::Stdout,
+                line: String::from("Setting user: Steve"),
+            },
+            LogLine {
+                source: LogSource::Stdout,
+                line: String::from("no newline"),
+            },
+            LogLine {
+                source: LogSource::Stderr,
+                line: String::from("crashed"),
+            },
+        ]
+    );
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    for line in ["Setting user: Steve", "crashed", "no newline"] {
+        assert!(log.lines().any(|x| x == line));
+    }
+}
+
+#[test]
+fn running_game_can_be_killed() {
+    let mut game = GameProcess::spawn(&shell("echo started; exec sleep 30"), None).unwrap();
+
+    assert_eq!(game.lines().next().unwrap().line, "started");
+    assert!(game.try_wait().unwrap().is_none());
+
+    game.kill().unwrap();
+    assert!(!game.wait().unwrap().success());
+}
+
+#[test]
+fn unread_output_is_bounded() {
+    let log_file = std::env::temp_dir()
+        .join(format!("cml-process-bounded-{}", std::process::id()))
+        .join("latest.log");
+    let count = LOG_BUFFER_LINES + 500;
+
+    let mut game = GameProcess::spawn(
+        &shell(&format!(
+            "i=0; while [ $i -lt {} ]; do echo $i; i=$((i+1)); done",
+            count
+        )),
+        Some(&log_file),
+    )
+    .unwrap();
+
+    // 没有读取输出时游戏也不会阻塞，多出的行被丢弃
+    assert!(game.wait().unwrap().success());
+    let lines: Vec<LogLine> = game.lines().collect();
+    assert_eq!(lines.len(), LOG_BUFFER_LINES);
+    assert_eq!(lines[0].line, "0");
+
+    // 日志文件中仍然是完整的输出
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    assert_eq!(log.lines().count(), count);
+}
@@ -0,0 +1,166 @@
+use command_minecraft_launcher::directory::GameDirectory;
+use command_minecraft_launcher::minecraft_core::{version_tasks, ArtifactKind};
+use command_minecraft_launcher::model::{self, VersionJson};
+use command_minecraft_launcher::rules::{resolve_arguments, version_arguments, Environment};
+use std::collections::HashMap;
+
+fn fixture(name: &str) -> VersionJson {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    model::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn env(os_name: &str, os_arch: &str, os_version: &str) -> Environment {
+    Environment {
+        os_name: os_name.to_string(),
+        os_arch: os_arch.to_string(),
+        os_version: os_version.to_string(),
+        features: HashMap::new(),
+    }
+}
+
+fn allowed_libraries(version: &VersionJson, env: &Environment) -> Vec<String> {
+    version
+        .libraries
+        .iter()
+        .filter(|x| x.is_allowed(env))
+        .map(|x| x.name.clone())
+        .collect()
+}
+
+#[test]
+fn later_rules_override_earlier_ones() {
+    let version = fixture("1.12.2.json");
+
+    let linux = allowed_libraries(&version, &env("linux", "x86_64", "6.1.0"));
+    assert!(linux.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209".to_string()));
+    assert!(!linux.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.2-nightly-20140822".to_string()));
+    assert!(!linux.contains(&"tv.twitch:twitch-platform:6.5".to_string()));
+
+    let osx = allowed_libraries(&version, &env("osx", "x86_64", "13.4"));
+    assert!(osx.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.2-nightly-20140822".to_string()));
+    assert!(!osx.contains(&"org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209".to_string()));
+    assert!(osx.contains(&"com.mojang:patchy:1.3.9".to_string()));
+}
+
+#[test]
+fn native_classifier_substitutes_arch() {
+    let version = fixture("1.12.2.json");
+    let twitch = version
+        .libraries
+        .iter()
+        .find(|x| x.name == "tv.twitch:twitch-platform:6.5")
+        .unwrap();
+
+    let windows_32 = env("windows", "x86", "10.0");
+    let windows_64 = env("windows", "x86_64", "10.0");
+
+    assert_eq!(
+        twitch.native_classifier(&windows_32).as_deref(),
+        Some("natives-windows-32")
+    );
+    assert!(twitch
+        .classifier(&twitch.native_classifier(&windows_64).unwrap())
+        .is_some());
+}
+
+#[test]
+fn downloads_follow_rules_and_native_classifier() {
+    let version = fixture("1.12.2.json");
+    let directory = GameDirectory::new("game");
+
+    let files = |env: &Environment, kind: ArtifactKind| -> Vec<String> {
+        version_tasks(&directory, "1.12.2", &version, env)
+            .unwrap()
+            .into_iter()
+            .filter(|(x, _)| *x == kind)
+            .map(|(_, task)| {
+                let path = std::path::PathBuf::from(task.path);
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            })
+            .collect()
+    };
+
+    let windows = files(&env("windows", "x86_64", "10.0"), ArtifactKind::Native);
+    assert!(windows.contains(&"twitch-platform-6.5-natives-windows-64.jar".to_string()));
+    assert!(!windows.contains(&"twitch-platform-6.5-natives-windows-32.jar".to_string()));
+    assert!(windows.iter().all(|x| !x.contains("natives-linux")));
+
+    let linux = env("linux", "x86_64", "6.1.0");
+    let natives = files(&linux, ArtifactKind::Native);
+    assert!(
+        natives.contains(&"lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar".to_string())
+    );
+    assert!(natives.iter().all(|x| x.contains("natives-linux")));
+
+    // 规则不允许的库不下载
+    let libraries = files(&linux, ArtifactKind::Library);
+    assert!(libraries.contains(&"lwjgl-2.9.4-nightly-20150209.jar".to_string()));
+    assert!(!libraries.contains(&"lwjgl-2.9.2-nightly-20140822.jar".to_string()));
+    assert!(!libraries.contains(&"twitch-platform-6.5.jar".to_string()));
+}
+
+#[test]
+fn jvm_arguments_match_os_name_version_and_arch() {
+    let version = fixture("1.19.json");
+    let jvm = &version.arguments.as_ref().unwrap().jvm;
+
+    let windows_10 = resolve_arguments(jvm, &env("windows", "x86_64", "10.0"));
+    assert!(windows_10.contains(&"-Dos.name=Windows 10".to_string()));
+    assert!(!windows_10.contains(&"-Xss1M".to_string()));
+
+    let windows_7_32 = resolve_arguments(jvm, &env("windows", "x86", "6.1"));
+    assert!(!windows_7_32.contains(&"-Dos.name=Windows 10".to_string()));
+    assert!(windows_7_32.contains(&"-Xss1M".to_string()));
+
+    let linux = resolve_arguments(jvm, &env("linux", "x86_64", "6.1.0"));
+    assert_eq!(linux[0], "-Djava.library.path=${natives_directory}");
+    assert_eq!(linux.last().unwrap(), "${classpath}");
+}
+
+#[test]
+fn game_arguments_match_features() {
+    let version = fixture("1.19.json");
+    let game = &version.arguments.as_ref().unwrap().game;
+    let linux = env("linux", "x86_64", "6.1.0");
+
+    let plain = resolve_arguments(game, &linux);
+    assert!(!plain.contains(&"--demo".to_string()));
+    assert!(!plain.contains(&"--width".to_string()));
+
+    let demo = resolve_arguments(game, &linux.clone().with_feature("is_demo_user", true));
+    assert!(demo.contains(&"--demo".to_string()));
+
+    let resolution = resolve_arguments(
+        game,
+        &linux
+            .with_feature("is_demo_user", false)
+            .with_feature("has_custom_resolution", true),
+    );
+    assert!(!resolution.contains(&"--demo".to_string()));
+    assert!(resolution.ends_with(&[
+        "--width".to_string(),
+        "${resolution_width}".to_string(),
+        "--height".to_string(),
+        "${resolution_height}".to_string(),
+    ]));
+}
+
+#[test]
+fn legacy_versions_use_minecraft_arguments() {
+    let version = fixture("1.12.2.json");
+    let linux = env("linux", "x86_64", "6.1.0").with_feature("is_demo_user", true);
+
+    let (jvm, game) = version_arguments(&version, &linux).unwrap();
+    assert_eq!(
+        jvm,
+        [
+            "-Djava.library.path=${natives_directory}",
+            "-Dminecraft.launcher.brand=${launcher_name}",
+            "-Dminecraft.launcher.version=${launcher_version}",
+            "-cp",
+            "${classpath}",
+        ]
+    );
+    assert_eq!(game[..2], ["--username", "${auth_player_name}"]);
+    assert_eq!(game.last().unwrap(), "--demo");
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use command_minecraft_launcher::directory::GameDirectory;
+use command_minecraft_launcher::downloader::downloader::{download, DownloadTask};
+use command_minecraft_launcher::minecraft_core::{ArtifactKind, DownloadManager};
+use command_minecraft_launcher::verify::{check_file, sha1_bytes, sha1_file, FileStatus};
+use common::MockServer;
+use serde_json::json;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cml-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn sha1_of_file_matches_bytes() {
+    let path = temp_path("hash.txt");
+    std::fs::write(&path, b"hello").unwrap();
+
+    assert_eq!(
+        sha1_file(&path).unwrap(),
+        "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+    );
+    assert_eq!(sha1_file(&path).unwrap(), sha1_bytes(b"hello"));
+    assert!(sha1_file(&temp_path("missing-hash.txt")).is_err());
+}
+
+#[test]
+fn files_are_checked_by_size_and_sha1() {
+    let path = temp_path("check.txt");
+    std::fs::write(&path, b"hello").unwrap();
+    let sha1 = sha1_bytes(b"hello");
+
+    assert_eq!(check_file(&path, Some(&sha1), Some(5)), FileStatus::Valid);
+    // 大写的 sha1 也可以
+    assert_eq!(
+        check_file(&path, Some(&sha1.to_uppercase()), Some(5)),
+        FileStatus::Valid
+    );
+    // 不指定时不校验
+    assert_eq!(check_file(&path, None, None), FileStatus::Valid);
+
+    assert_eq!(
+        check_file(&temp_path("missing.txt"), Some(&sha1), Some(5)),
+        FileStatus::Missing
+    );
+    assert_eq!(
+        check_file(&path, Some(&sha1), Some(6)),
+        FileStatus::SizeMismatch {
+            expected: 6,
+            actual: 5
+        }
+    );
+    assert_eq!(
+        check_file(&path, Some(&"0".repeat(40)), Some(5)),
+        FileStatus::HashMismatch {
+            expected: "0".repeat(40),
+            actual: sha1.clone(),
+        }
+    );
+
+    let task =
+        DownloadTask::new(path.to_string_lossy().into_owned(), String::new()).checked(&sha1, 5);
+    assert!(task.check().is_valid());
+}
+
+#[tokio::test]
+async fn corrupt_file_is_downloaded_again() {
+    let server = MockServer::start(vec![("/file.txt", 200, String::from("hello"))]);
+    let path = temp_path("corrupt.txt");
+    std::fs::write(&path, b"jello").unwrap();
+
+    let task = DownloadTask::new(
+        path.to_string_lossy().into_owned(),
+        format!("{}/file.txt", server.root),
+    )
+    .checked(&sha1_bytes(b"hello"), 5);
+
+    // 大小相同但内容损坏的文件不能跳过
+    assert!(matches!(task.check(), FileStatus::HashMismatch { .. }));
+
+    // 与 download_version 相同，先删除损坏的文件再下载
+    std::fs::remove_file(&path).unwrap();
+    download(&task.path, &task.url).await.unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+    assert!(task.check().is_valid());
+    assert_eq!(server.requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn broken_files_are_reported_and_repaired() {
+    let client = String::from("client");
+    let library = String::from("library");
+    let index = String::from(r#"{"objects":{}}"#);
+    let server = MockServer::start(vec![
+        ("/client.jar", 200, client.clone()),
+        ("/lib.jar", 200, library.clone()),
+        ("/index.json", 200, index.clone()),
+    ]);
+
+    let root = std::env::temp_dir().join(format!("cml-repair-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let directory = GameDirectory::new(&root);
+
+    let version = json!({
+        "id": "test",
+        "type": "release",
+        "mainClass": "net.minecraft.client.main.Main",
+        "downloads": {
+            "client": { "sha1": sha1_bytes(client.as_bytes()), "size": client.len(), "url": format!("{}/client.jar", server.root) },
+        },
+        "libraries": [{
+            "name": "com.example:lib:1.0",
+            "downloads": {
+                "artifact": { "path": "com/example/lib/1.0/lib-1.0.jar", "sha1": sha1_bytes(library.as_bytes()), "size": library.len(), "url": format!("{}/lib.jar", server.root) },
+            },
+        }],
+        "assetIndex": { "id": "test", "sha1": sha1_bytes(index.as_bytes()), "size": index.len(), "url": format!("{}/index.json", server.root) },
+        "assets": "test",
+    });
+    std::fs::create_dir_all(directory.version_dir("test")).unwrap();
+    std::fs::write(directory.version_json("test"), version.to_string()).unwrap();
+    std::fs::write(directory.version_jar("test"), &client).unwrap();
+
+    // 库文件大小正确但内容损坏，资源索引不存在
+    let library_path = directory.library("com/example/lib/1.0/lib-1.0.jar");
+    std::fs::create_dir_all(library_path.parent().unwrap()).unwrap();
+    std::fs::write(&library_path, b"LIBRARY").unwrap();
+
+    let manager = DownloadManager::new().with_directory(directory.clone());
+    let report = manager.verify_version("test").unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.checked, 3);
+
+    let status = |kind: ArtifactKind| {
+        let problems: Vec<&FileStatus> = report
+            .problems
+            .iter()
+            .filter(|x| x.kind == kind)
+            .map(|x| &x.status)
+            .collect();
+        assert_eq!(problems.len(), 1, "{}", kind);
+        problems[0].clone()
+    };
+    assert_eq!(report.problems.len(), 2);
+    assert!(matches!(
+        status(ArtifactKind::Library),
+        FileStatus::HashMismatch { .. }
+    ));
+    assert_eq!(status(ArtifactKind::AssetIndex), FileStatus::Missing);
+
+    manager.repair_version("test").unwrap();
+
+    // 只重新下载有问题的两个文件
+    let mut requested: Vec<String> = server
+        .requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|x| x.path.clone())
+        .collect();
+    requested.sort();
+    assert_eq!(requested, ["/index.json", "/lib.jar"]);
+
+    assert_eq!(std::fs::read_to_string(&library_path).unwrap(), library);
+    assert!(manager.verify_version("test").unwrap().is_ok());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
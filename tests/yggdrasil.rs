@@ -0,0 +1,226 @@
+mod common;
+
+use command_minecraft_launcher::auth::LoginError;
+use command_minecraft_launcher::verify::sha256_bytes;
+use command_minecraft_launcher::yggdrasil::{
+    download_authlib_injector, fetch_metadata, AuthlibInjector, YggdrasilAccount,
+};
+use common::MockServer;
+use serde_json::json;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+fn profile() -> serde_json::Value {
+    json!({ "id": "ec561538f3fd461daff5086b22154bce", "name": "Alex" })
+}
+
+fn account(server: &MockServer) -> YggdrasilAccount {
+    YggdrasilAccount {
+        server: server.root.clone(),
+        name: String::from("Alex"),
+        uuid: String::from("ec561538f3fd461daff5086b22154bce"),
+        access_token: String::from("old-token"),
+        client_token: String::from("client-token"),
+    }
+}
+
+#[tokio::test]
+async fn authenticate_uses_selected_profile() {
+    let server = MockServer::start(vec![(
+        "/authserver/authenticate",
+        200,
+        json!({
+            "accessToken": "access-token",
+            "clientToken": "client-token",
+            "selectedProfile": profile(),
+            "availableProfiles": [profile()],
+        })
+        .to_string(),
+    )]);
+
+    let account =
+        YggdrasilAccount::authenticate(&format!("{}/", server.root), "alex@example.com", "secret")
+            .await
+            .unwrap();
+
+    assert_eq!(account.server, server.root);
+    assert_eq!(account.name, "Alex");
+    assert_eq!(account.uuid, "ec561538f3fd461daff5086b22154bce");
+    assert_eq!(account.access_token, "access-token");
+
+    let requests = server.requests.lock().unwrap();
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["username"], "alex@example.com");
+    assert_eq!(body["password"], "secret");
+    assert_eq!(body["agent"]["name"], "Minecraft");
+}
+
+#[tokio::test]
+async fn authenticate_selects_first_profile() {
+    let server = MockServer::start(vec![
+        (
+            "/authserver/authenticate",
+            200,
+            json!({
+                "accessToken": "access-token",
+                "clientToken": "client-token",
+                "availableProfiles": [profile()],
+            })
+            .to_string(),
+        ),
+        (
+            "/authserver/refresh",
+            200,
+            json!({
+                "accessToken": "selected-token",
+                "clientToken": "client-token",
+                "selectedProfile": profile(),
+            })
+            .to_string(),
+        ),
+    ]);
+
+    let account = YggdrasilAccount::authenticate(&server.root, "alex@example.com", "secret")
+        .await
+        .unwrap();
+    assert_eq!(account.name, "Alex");
+    assert_eq!(account.access_token, "selected-token");
+
+    let requests = server.requests.lock().unwrap();
+    let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+    assert_eq!(body["selectedProfile"]["name"], "Alex");
+}
+
+#[tokio::test]
+async fn authenticate_reports_error_message() {
+    let server = MockServer::start(vec![(
+        "/authserver/authenticate",
+        403,
+        json!({
+            "error": "ForbiddenOperationException",
+            "errorMessage": "Invalid credentials. Invalid username or password.",
+        })
+        .to_string(),
+    )]);
+
+    let err = YggdrasilAccount::authenticate(&server.root, "alex@example.com", "wrong")
+        .await
+        .unwrap_err();
+
+    match err {
+        LoginError::Service {
+            status, message, ..
+        } => {
+            assert_eq!(status, 403);
+            assert_eq!(
+                message,
+                "Invalid credentials. Invalid username or password."
+            );
+        }
+        err => panic!("unexpected error {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn validate_refresh_and_invalidate() {
+    let server = MockServer::start(vec![
+        ("/authserver/validate", 403, String::new()),
+        ("/authserver/validate", 204, String::new()),
+        (
+            "/authserver/refresh",
+            200,
+            json!({
+                "accessToken": "new-token",
+                "clientToken": "client-token",
+                "selectedProfile": profile(),
+            })
+            .to_string(),
+        ),
+        ("/authserver/invalidate", 204, String::new()),
+    ]);
+    let account = account(&server);
+
+    assert!(!account.validate().await.unwrap());
+    assert!(account.validate().await.unwrap());
+
+    let refreshed = account.refresh().await.unwrap();
+    assert_eq!(refreshed.access_token, "new-token");
+    assert_eq!(refreshed.name, "Alex");
+
+    refreshed.invalidate().await.unwrap();
+
+    let requests = server.requests.lock().unwrap();
+    assert!(requests.last().unwrap().body.contains("new-token"));
+}
+
+#[tokio::test]
+async fn authlib_injector_is_downloaded_and_verified() {
+    let jar = String::from("not really a jar");
+    let latest = |sha256: &str| {
+        json!({
+            "build_number": 53,
+            "version": "1.2.5",
+            "download_url": "",
+            "checksums": { "sha256": sha256 },
+        })
+    };
+
+    let server = MockServer::start(vec![("/authlib-injector.jar", 200, jar.clone())]);
+    let download_url = format!("{}/authlib-injector.jar", server.root);
+
+    let mut good = latest(&sha256_bytes(jar.as_bytes()));
+    good["download_url"] = json!(download_url);
+    let mut bad = latest(&"0".repeat(64));
+    bad["download_url"] = json!(download_url);
+    let mut evil = latest(&sha256_bytes(jar.as_bytes()));
+    evil["download_url"] = json!(download_url);
+    evil["version"] = json!("../../x");
+
+    let server = MockServer::start(vec![
+        ("/good.json", 200, good.to_string()),
+        ("/bad.json", 200, bad.to_string()),
+        ("/evil.json", 200, evil.to_string()),
+        ("/", 200, json!({ "meta": {} }).to_string()),
+    ]);
+
+    let libraries: PathBuf =
+        std::env::temp_dir().join(format!("cml-yggdrasil-{}", std::process::id()));
+
+    let path = download_authlib_injector(&format!("{}/good.json", server.root), &libraries)
+        .await
+        .unwrap();
+    assert!(path
+        .ends_with("moe/yushi/authlibinjector/authlib-injector/1.2.5/authlib-injector-1.2.5.jar"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), jar);
+
+    assert!(
+        download_authlib_injector(&format!("{}/bad.json", server.root), &libraries)
+            .await
+            .is_err()
+    );
+
+    // 版本号不能跳出 libraries 文件夹
+    let err = download_authlib_injector(&format!("{}/evil.json", server.root), &libraries)
+        .await
+        .unwrap_err();
+    assert!(err.contains("../../x"), "{}", err);
+    assert!(!libraries.join("moe/yushi/authlibinjector/x").exists());
+
+    let injector = AuthlibInjector {
+        jar: path.clone(),
+        api_root: server.root.clone(),
+        metadata: fetch_metadata(&format!("{}/", server.root)).await.unwrap(),
+    };
+
+    let mut javaagent = OsString::from("-javaagent:");
+    javaagent.push(&path);
+    javaagent.push(format!("={}", server.root));
+
+    assert_eq!(
+        injector.jvm_arguments(),
+        [
+            javaagent,
+            OsString::from("-Dauthlibinjector.yggdrasil.prefetched=eyJtZXRhIjp7fX0="),
+        ]
+    );
+}